@@ -0,0 +1,179 @@
+use gdk;
+use glib::object::Cast;
+use gtk;
+use gtk::prelude::{
+    CheckMenuItemExt, ContainerExt, GtkMenuExt, GtkMenuExtManual, GtkMenuItemExt, MenuShellExt,
+    TreeSortableExtManual, TreeViewColumnExt, TreeViewExt, WidgetExt,
+};
+use gtk::{Inhibit, SortColumn, SortType};
+
+use settings::{ColumnSettings, TreeViewSettings};
+
+/// Allows the user to reorder the columns of the tree view by dragging their header and to
+/// show/hide or move them from a menu displayed when right-clicking on a header.
+pub fn setup_column_chooser(tree: &gtk::TreeView) {
+    for column in tree.get_columns() {
+        column.set_reorderable(true);
+        let button = match column.get_button() {
+            Some(button) => button,
+            None => continue,
+        };
+        button.connect_button_press_event(
+            clone!(@weak tree, @weak column => @default-return Inhibit(false), move |_, event| {
+                if event.get_event_type() == gdk::EventType::ButtonPress && event.get_button() == 3 {
+                    show_column_menu(&tree, &column, event);
+                    Inhibit(true)
+                } else {
+                    Inhibit(false)
+                }
+            }),
+        );
+    }
+}
+
+fn show_column_menu(tree: &gtk::TreeView, clicked: &gtk::TreeViewColumn, event: &gdk::EventButton) {
+    let menu = gtk::Menu::new();
+    let columns = tree.get_columns();
+
+    for column in &columns {
+        let title = column
+            .get_title()
+            .map(|t| t.to_string())
+            .unwrap_or_default();
+        let item = gtk::CheckMenuItem::new_with_label(&title);
+
+        item.set_active(column.get_visible());
+        item.connect_toggled(clone!(@weak column => move |item| {
+            column.set_visible(item.get_active());
+        }));
+        menu.append(&item);
+    }
+    // We don't want the user to be able to hide all columns.
+    if columns.iter().filter(|c| c.get_visible()).count() < 2 {
+        for (item, column) in menu.get_children().iter().zip(columns.iter()) {
+            if column.get_visible() {
+                item.set_sensitive(false);
+            }
+        }
+    }
+
+    let pos = columns.iter().position(|c| c == clicked).unwrap_or(0);
+    let move_left = gtk::MenuItem::new_with_label("Move column left");
+    let move_right = gtk::MenuItem::new_with_label("Move column right");
+
+    move_left.set_sensitive(pos > 0);
+    move_right.set_sensitive(pos + 1 < columns.len());
+    move_left.connect_activate(clone!(@weak tree, @weak clicked => move |_| {
+        move_column(&tree, &clicked, false);
+    }));
+    move_right.connect_activate(clone!(@weak tree, @weak clicked => move |_| {
+        move_column(&tree, &clicked, true);
+    }));
+    menu.append(&gtk::SeparatorMenuItem::new());
+    menu.append(&move_left);
+    menu.append(&move_right);
+
+    menu.set_property_attach_widget(Some(tree));
+    menu.show_all();
+    menu.popup_easy(event.get_button(), event.get_time());
+}
+
+fn move_column(tree: &gtk::TreeView, column: &gtk::TreeViewColumn, to_right: bool) {
+    let columns = tree.get_columns();
+    let pos = match columns.iter().position(|c| c == column) {
+        Some(pos) => pos,
+        None => return,
+    };
+    if to_right {
+        if let Some(next) = columns.get(pos + 1) {
+            tree.move_column_after(column, Some(next));
+        }
+    } else if pos > 1 {
+        tree.move_column_after(column, Some(&columns[pos - 2]));
+    } else if pos == 1 {
+        tree.move_column_after(column, None::<&gtk::TreeViewColumn>);
+    }
+}
+
+/// Returns the current state of the columns (order, visibility and width) and of the sorting.
+pub fn get_tree_view_settings(tree: &gtk::TreeView) -> TreeViewSettings {
+    let sorted = tree
+        .get_model()
+        .and_then(|model| model.dynamic_cast::<gtk::TreeSortable>().ok())
+        .and_then(|model| model.get_sort_column_id());
+    let (sort_column, sort_ascending) = match sorted {
+        Some((SortColumn::Index(index), order)) => (
+            // The model indexes can change between versions so we store the title of the column
+            // instead.
+            tree.get_columns()
+                .iter()
+                .find(|c| c.get_sort_column_id() == index as i32)
+                .and_then(|c| c.get_title())
+                .map(|t| t.to_string()),
+            order == SortType::Ascending,
+        ),
+        _ => (None, true),
+    };
+
+    TreeViewSettings {
+        sort_column,
+        sort_ascending,
+        columns: tree
+            .get_columns()
+            .iter()
+            .map(|column| ColumnSettings {
+                title: column
+                    .get_title()
+                    .map(|t| t.to_string())
+                    .unwrap_or_default(),
+                visible: column.get_visible(),
+                width: column.get_width(),
+            })
+            .collect(),
+    }
+}
+
+/// Applies back the settings returned by `get_tree_view_settings`.
+pub fn restore_tree_view_settings(tree: &gtk::TreeView, settings: &TreeViewSettings) {
+    let columns = tree.get_columns();
+    let mut previous: Option<gtk::TreeViewColumn> = None;
+
+    for saved in &settings.columns {
+        let column = match columns
+            .iter()
+            .find(|c| c.get_title().map(|t| t == saved.title.as_str()) == Some(true))
+        {
+            Some(column) => column,
+            None => continue,
+        };
+        tree.move_column_after(column, previous.as_ref());
+        column.set_visible(saved.visible);
+        if saved.width > 0 {
+            column.set_fixed_width(saved.width);
+        }
+        previous = Some(column.clone());
+    }
+
+    let sort_column = settings.sort_column.as_ref().and_then(|title| {
+        columns
+            .iter()
+            .find(|c| c.get_title().map(|t| t == title.as_str()) == Some(true))
+            .map(|c| c.get_sort_column_id())
+            .filter(|&id| id >= 0)
+    });
+    if let Some(sort_column) = sort_column {
+        if let Some(model) = tree
+            .get_model()
+            .and_then(|model| model.dynamic_cast::<gtk::TreeSortable>().ok())
+        {
+            model.set_sort_column_id(
+                SortColumn::Index(sort_column as u32),
+                if settings.sort_ascending {
+                    SortType::Ascending
+                } else {
+                    SortType::Descending
+                },
+            );
+        }
+    }
+}
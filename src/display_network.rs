@@ -1,3 +1,4 @@
+use columns;
 use network_dialog::{self, NetworkDialog};
 
 use gtk;
@@ -47,6 +48,7 @@ fn append_column(
 
 pub struct Network {
     list_store: gtk::ListStore,
+    pub tree: gtk::TreeView,
     pub filter_entry: gtk::Entry,
     pub search_bar: gtk::SearchBar,
     dialogs: Rc<RefCell<Vec<NetworkDialog>>>,
@@ -148,6 +150,7 @@ impl Network {
         for (pos, column) in columns.iter().enumerate() {
             column.set_sort_column_id(pos as i32 + columns_len as i32);
         }
+        columns::setup_column_chooser(&tree);
 
         let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
         let horizontal_layout = gtk::Grid::new();
@@ -222,6 +225,7 @@ impl Network {
 
         Network {
             list_store,
+            tree,
             filter_entry,
             search_bar,
             dialogs,
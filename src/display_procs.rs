@@ -1,35 +1,134 @@
-use glib::object::Cast;
+use gdk;
+use glib;
+use glib::object::{Cast, IsA};
 use glib::Type;
 use gtk;
 use gtk::prelude::{
-    BoxExt, ButtonExt, CellLayoutExt, CellRendererExt, ContainerExt, EntryExt, GridExt,
-    GtkListStoreExtManual, GtkWindowExt, OverlayExt, SearchBarExt, TreeModelExt,
-    TreeModelFilterExt, TreeSelectionExt, TreeViewColumnExt, TreeViewExt, WidgetExt,
+    BoxExt, ButtonExt, CellLayoutExt, CellRendererExt, CellRendererTextExt, ComboBoxExt,
+    ComboBoxTextExt, ContainerExt, EditableSignals, EntryExt, GridExt, GtkMenuExtManual,
+    GtkWindowExt, LabelExt, MenuButtonExt, OverlayExt, SearchBarExt, StyleContextExt,
+    ToggleButtonExt, TreeModelExt, TreeModelFilterExt, TreeSelectionExt, TreeStoreExtManual,
+    TreeViewColumnExt, TreeViewExt, WidgetExt,
 };
+use gtk::{GtkMenuExt, Inhibit};
+use pango;
 
 use sysinfo::{AsU32, Pid, Process, ProcessExt};
 
+use columns;
 use notebook::NoteBook;
+use procfs::get_cgroup_unit;
+use query::{Field, Query, QueryOptions, Row};
 
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
 
-use utils::{create_button_with_image, format_number};
+use scheduling::get_priority;
+use signals::{build_process_menu, STOPPED_STATE};
+use utils::{
+    create_button_with_image, format_date, format_number, get_process_user, get_thread_count,
+    ProcessIdentity, StatusBar,
+};
+
+// The columns of the process model. The first ones are displayed in the tree view (in this
+// order), the other ones are used as keys when sorting.
+pub const COL_PID: u32 = 0;
+pub const COL_NAME: u32 = 1;
+pub const COL_CPU: u32 = 2;
+pub const COL_MEM: u32 = 3;
+pub const COL_DISK_READ: u32 = 4;
+pub const COL_DISK_WRITE: u32 = 5;
+pub const COL_USER: u32 = 6;
+pub const COL_STATE: u32 = 7;
+pub const COL_THREADS: u32 = 8;
+pub const COL_NICE: u32 = 9;
+pub const COL_START_TIME: u32 = 10;
+pub const COL_VIRTUAL_MEM: u32 = 11;
+pub const COL_PARENT_PID: u32 = 12;
+pub const COL_SUBTREE_CPU: u32 = 13;
+pub const COL_SUBTREE_MEM: u32 = 14;
+pub const COL_NAME_LOWERCASE: u32 = 15;
+pub const COL_CPU_F32: u32 = 16;
+pub const COL_MEM_U64: u32 = 17;
+pub const COL_DISK_READ_U64: u32 = 18;
+pub const COL_DISK_WRITE_U64: u32 = 19;
+pub const COL_THREADS_U32: u32 = 20;
+pub const COL_NICE_I32: u32 = 21;
+pub const COL_START_TIME_U64: u32 = 22;
+pub const COL_VIRTUAL_MEM_U64: u32 = 23;
+pub const COL_PARENT_PID_U32: u32 = 24;
+pub const COL_SUBTREE_CPU_F32: u32 = 25;
+pub const COL_SUBTREE_MEM_U64: u32 = 26;
+// Only used by the filter.
+pub const COL_CMD: u32 = 27;
+pub const COL_EXE: u32 = 28;
+// The group displayed in the row. It's only set for the rows of the grouping mode.
+pub const COL_GROUP: u32 = 29;
+
+/// How the processes are aggregated in the process list.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    User,
+    Name,
+    Cgroup,
+}
+
+impl GroupBy {
+    const ALL: &'static [GroupBy] = &[GroupBy::User, GroupBy::Name, GroupBy::Cgroup];
+
+    fn get_id(self) -> &'static str {
+        match self {
+            GroupBy::User => "user",
+            GroupBy::Name => "name",
+            GroupBy::Cgroup => "cgroup",
+        }
+    }
+
+    fn get_label(self) -> &'static str {
+        match self {
+            GroupBy::User => "Group by user",
+            GroupBy::Name => "Group by name",
+            GroupBy::Cgroup => "Group by unit",
+        }
+    }
+
+    pub fn get_group(self, pro: &Process) -> String {
+        match self {
+            GroupBy::User => get_process_user(pro),
+            GroupBy::Name => pro.name().to_owned(),
+            GroupBy::Cgroup => get_cgroup_unit(pro.pid()).unwrap_or_else(|| "unknown".to_owned()),
+        }
+    }
+}
+
+/// The id used in the "group by" combo box for the grouping mode.
+pub fn get_group_by_id(group_by: Option<GroupBy>) -> &'static str {
+    group_by.map(GroupBy::get_id).unwrap_or("none")
+}
 
 #[allow(dead_code)]
 pub struct Procs {
     pub left_tree: gtk::TreeView,
     pub scroll: gtk::ScrolledWindow,
-    pub current_pid: Rc<Cell<Option<Pid>>>,
+    pub current_pids: Rc<RefCell<HashMap<Pid, ProcessIdentity>>>,
     pub kill_button: gtk::Button,
+    pub pause_button: gtk::Button,
+    pub resume_button: gtk::Button,
+    pub signal_button: gtk::MenuButton,
     pub info_button: gtk::Button,
+    pub tree_button: gtk::ToggleButton,
     pub vertical_layout: gtk::Box,
-    pub list_store: gtk::ListStore,
+    pub tree_store: gtk::TreeStore,
+    pub tree_mode: Rc<Cell<bool>>,
+    pub group_combo: gtk::ComboBoxText,
+    pub group_by: Rc<Cell<Option<GroupBy>>>,
     pub columns: Vec<gtk::TreeViewColumn>,
     pub filter_entry: gtk::Entry,
     pub search_bar: gtk::SearchBar,
     pub filter_button: gtk::Button,
+    pub status_bar: StatusBar,
 }
 
 impl Procs {
@@ -37,12 +136,22 @@ impl Procs {
         proc_list: &HashMap<Pid, Process>,
         note: &mut NoteBook,
         window: &gtk::ApplicationWindow,
+        refresh_interval: f64,
     ) -> Procs {
         let left_tree = gtk::TreeView::new();
         let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
-        let current_pid = Rc::new(Cell::new(None));
+        let current_pids = Rc::new(RefCell::new(HashMap::new()));
         let kill_button = gtk::Button::new_with_label("End task");
+        let pause_button = gtk::Button::new_with_label("Pause");
+        let resume_button = gtk::Button::new_with_label("Resume");
+        let signal_button = gtk::MenuButton::new();
+        let process_menu = build_process_menu();
+        let status_bar = StatusBar::new();
         let info_button = gtk::Button::new_with_label("More information");
+        let tree_button = gtk::ToggleButton::new_with_label("Process tree");
+        let tree_mode = Rc::new(Cell::new(false));
+        let group_combo = gtk::ComboBoxText::new();
+        let group_by = Rc::new(Cell::new(None));
 
         let filter_button =
             create_button_with_image(include_bytes!("../assets/magnifier.png"), "Filter");
@@ -52,41 +161,75 @@ impl Procs {
         let filter_entry = gtk::Entry::new();
         let search_bar = gtk::SearchBar::new();
 
+        let filter_box = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+        // Errors in the filter query are displayed next to the filter entry.
+        let filter_error = gtk::Label::new(None);
+        let filter_query: Rc<RefCell<Option<Query>>> = Rc::new(RefCell::new(None));
+
         // We put the filter entry at the right bottom.
-        filter_entry.set_halign(gtk::Align::End);
-        filter_entry.set_valign(gtk::Align::End);
+        filter_box.set_halign(gtk::Align::End);
+        filter_box.set_valign(gtk::Align::End);
         filter_entry.hide(); // By default, we don't show it.
+        filter_error.set_no_show_all(true);
         search_bar.connect_entry(&filter_entry);
         search_bar.set_show_close_button(true);
 
-        overlay.add_overlay(&filter_entry);
+        let filter_options = FilterOptions::new();
+
+        filter_box.pack_start(&filter_error, false, false, 0);
+        filter_box.pack_start(&filter_entry, false, false, 0);
+        filter_box.pack_start(&filter_options.container, false, false, 0);
+        overlay.add_overlay(&filter_box);
+
+        // The filter options are only displayed alongside the filter entry.
+        filter_entry.connect_property_visible_notify(
+            clone!(@weak filter_options.container as container => move |entry| {
+                container.set_visible(entry.get_visible());
+            }),
+        );
 
         let mut columns: Vec<gtk::TreeViewColumn> = Vec::new();
 
-        let list_store = gtk::ListStore::new(&[
-            // The first four columns of the model are going to be visible in the view.
+        let tree_store = gtk::TreeStore::new(&[
+            // The first fifteen columns of the model are going to be visible in the view.
             Type::U32,    // pid
             Type::String, // name
             Type::String, // CPU
             Type::String, // mem
-            Type::String, // disk I/O
-            // These two will serve as keys when sorting by process name and CPU usage.
+            Type::String, // disk read
+            Type::String, // disk write
+            Type::String, // user
+            Type::String, // state
+            Type::String, // threads
+            Type::String, // nice
+            Type::String, // start time
+            Type::String, // virtual mem
+            Type::String, // parent pid
+            Type::String, // subtree CPU
+            Type::String, // subtree mem
+            // These ones will serve as keys when sorting by process name, CPU usage, etc.
             Type::String, // name_lowercase
             Type::F32,    // CPU_f32
             Type::U64,    // mem
-            Type::U64,    // disk I/O
+            Type::U64,    // disk read
+            Type::U64,    // disk write
+            Type::U32,    // threads
+            Type::I32,    // nice
+            Type::U64,    // start time
+            Type::U64,    // virtual mem
+            Type::U32,    // parent pid
+            Type::F32,    // subtree CPU_f32
+            Type::U64,    // subtree mem
+            // These ones are only used when filtering.
+            Type::String, // command line
+            Type::String, // executable path
+            Type::String, // group
         ]);
 
         for pro in proc_list.values() {
-            create_and_fill_model(
-                &list_store,
-                pro.pid().as_u32(),
-                &pro.cmd(),
-                &pro.name(),
-                pro.cpu_usage(),
-                pro.memory() * 1_000,
-            );
+            create_and_fill_model(&tree_store, None, pro, refresh_interval);
         }
+        update_subtree_totals(&tree_store, None);
 
         left_tree.set_headers_visible(true);
         scroll.add(&left_tree);
@@ -94,27 +237,62 @@ impl Procs {
         let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
         let horizontal_layout = gtk::Grid::new();
 
-        left_tree.connect_cursor_changed(
-            clone!(@weak current_pid, @weak kill_button, @weak info_button => move |tree_view| {
-                let selection = tree_view.get_selection();
-                let (pid, ret) = if let Some((model, iter)) = selection.get_selected() {
-                    if let Ok(Some(x)) = model.get_value(&iter, 0).get::<u32>() {
-                        (Some(x as Pid), true)
-                    } else {
-                        (None, false)
+        signal_button.set_label("Actions");
+        signal_button.set_menu_model(Some(&process_menu));
+
+        // The same actions are available by right-clicking on a process.
+        let context_menu = gtk::Menu::new_from_model(&process_menu);
+        context_menu.set_property_attach_widget(Some(&left_tree));
+        left_tree.connect_button_press_event(
+            clone!(@weak context_menu => @default-return Inhibit(false), move |tree_view, event| {
+                if event.get_event_type() != gdk::EventType::ButtonPress || event.get_button() != 3 {
+                    return Inhibit(false);
+                }
+                let (x, y) = event.get_position();
+                if let Some((Some(path), _, _, _)) = tree_view.get_path_at_pos(x as _, y as _) {
+                    // We select the row under the cursor before showing the menu (unless it's
+                    // already part of the selection).
+                    if !tree_view.get_selection().path_is_selected(&path) {
+                        tree_view.set_cursor(&path, None::<&gtk::TreeViewColumn>, false);
                     }
-                } else {
-                    (None, false)
-                };
-                current_pid.set(pid);
+                    context_menu.popup_easy(event.get_button(), event.get_time());
+                }
+                Inhibit(true)
+            }),
+        );
+
+        let selection = left_tree.get_selection();
+        selection.set_mode(gtk::SelectionMode::Multiple);
+        selection.connect_changed(
+            clone!(@weak current_pids, @weak kill_button, @weak pause_button, @weak resume_button, @weak signal_button, @weak info_button => move |selection| {
+                let (paths, model) = selection.get_selected_rows();
+                let mut current_pids = current_pids.borrow_mut();
+
+                current_pids.clear();
+                for path in paths {
+                    if let Some(identity) = model
+                        .get_iter(&path)
+                        .and_then(|iter| get_row_identity(&model, &iter))
+                    {
+                        current_pids.insert(identity.pid, identity);
+                    }
+                }
+                let ret = !current_pids.is_empty();
                 kill_button.set_sensitive(ret);
+                pause_button.set_sensitive(ret);
+                resume_button.set_sensitive(ret);
+                signal_button.set_sensitive(ret);
                 info_button.set_sensitive(ret);
             }),
         );
         kill_button.set_sensitive(false);
+        pause_button.set_sensitive(false);
+        resume_button.set_sensitive(false);
+        signal_button.set_sensitive(false);
         info_button.set_sensitive(false);
 
         vertical_layout.pack_start(&overlay, true, true, 0);
+        vertical_layout.pack_start(&status_bar.info_bar, false, true, 0);
         horizontal_layout.attach(&info_button, 0, 0, 4, 1);
         horizontal_layout.attach_next_to(
             &kill_button,
@@ -124,9 +302,44 @@ impl Procs {
             1,
         );
         horizontal_layout.attach_next_to(
-            &filter_button,
+            &pause_button,
             Some(&kill_button),
             gtk::PositionType::Right,
+            2,
+            1,
+        );
+        horizontal_layout.attach_next_to(
+            &resume_button,
+            Some(&pause_button),
+            gtk::PositionType::Right,
+            2,
+            1,
+        );
+        horizontal_layout.attach_next_to(
+            &signal_button,
+            Some(&resume_button),
+            gtk::PositionType::Right,
+            2,
+            1,
+        );
+        horizontal_layout.attach_next_to(
+            &tree_button,
+            Some(&signal_button),
+            gtk::PositionType::Right,
+            2,
+            1,
+        );
+        horizontal_layout.attach_next_to(
+            &group_combo,
+            Some(&tree_button),
+            gtk::PositionType::Right,
+            2,
+            1,
+        );
+        horizontal_layout.attach_next_to(
+            &filter_button,
+            Some(&group_combo),
+            gtk::PositionType::Right,
             1,
             1,
         );
@@ -134,34 +347,17 @@ impl Procs {
         vertical_layout.pack_start(&horizontal_layout, false, true, 0);
 
         // The filter part.
-        let filter_model = gtk::TreeModelFilter::new(&list_store, None);
+        let filter_model = gtk::TreeModelFilter::new(&tree_store, None);
         filter_model.set_visible_func(
-            clone!(@weak filter_entry => @default-return false, move |model, iter| {
-                if !filter_entry.get_visible() || filter_entry.get_text_length() < 1 {
+            clone!(@weak filter_entry, @weak filter_query => @default-return false, move |model, iter| {
+                if !filter_entry.get_visible() {
                     return true;
                 }
-                if let Some(text) = filter_entry.get_text() {
-                    if text.is_empty() {
-                        return true;
-                    }
-                    let text: &str = text.as_ref();
-                    // TODO: Maybe add an option to make searches case sensitive?
-                    let pid = model.get_value(iter, 0)
-                                   .get::<u32>()
-                                   .unwrap_or_else(|_| None)
-                                   .map(|p| p.to_string())
-                                   .unwrap_or_else(String::new);
-                    let name = model.get_value(iter, 1)
-                                    .get::<String>()
-                                    .unwrap_or_else(|_| None)
-                                    .map(|s| s.to_lowercase())
-                                    .unwrap_or_else(String::new);
-                    pid.contains(text) ||
-                    text.contains(&pid) ||
-                    name.contains(text) ||
-                    text.contains(&name)
-                } else {
-                    true
+                let query = filter_query.borrow();
+                match *query {
+                    // In tree mode, a parent has to stay visible if one of its children matches.
+                    Some(ref query) => is_row_or_child_matching(model, iter, query),
+                    None => true,
                 }
             }),
         );
@@ -170,61 +366,138 @@ impl Procs {
         let sort_model = gtk::TreeModelSort::new(&filter_model);
         left_tree.set_model(Some(&sort_model));
 
+        left_tree.set_enable_tree_lines(true);
+
         append_column("pid", &mut columns, &left_tree, None);
         append_column("process name", &mut columns, &left_tree, Some(200));
         append_column("cpu usage", &mut columns, &left_tree, None);
         append_column("memory usage", &mut columns, &left_tree, None);
         #[cfg(not(windows))]
         {
-            append_column("disk I/O usage", &mut columns, &left_tree, None);
+            append_column("disk read", &mut columns, &left_tree, None);
+            append_column("disk write", &mut columns, &left_tree, None);
         }
         #[cfg(windows)]
         {
-            append_column("I/O usage", &mut columns, &left_tree, None);
+            append_column("I/O read", &mut columns, &left_tree, None);
+            append_column("I/O write", &mut columns, &left_tree, None);
         }
+        append_column("user", &mut columns, &left_tree, None);
+        append_column("state", &mut columns, &left_tree, None);
+        append_column("threads", &mut columns, &left_tree, None);
+        append_column("nice", &mut columns, &left_tree, None);
+        append_column("start time", &mut columns, &left_tree, None);
+        append_column("virtual memory", &mut columns, &left_tree, None);
+        append_column("parent pid", &mut columns, &left_tree, None);
+        append_column("subtree cpu usage", &mut columns, &left_tree, None);
+        append_column("subtree memory usage", &mut columns, &left_tree, None);
 
         // When we click the "name" column the order is defined by the
         // "name_lowercase" effectively making the built-in comparator ignore case.
-        columns[1].set_sort_column_id(5);
+        columns[COL_NAME as usize].set_sort_column_id(COL_NAME_LOWERCASE as _);
         // Likewise clicking the "CPU" column sorts by the "CPU_f32" one because
         // we want the order to be numerical not lexicographical.
-        columns[2].set_sort_column_id(6);
+        columns[COL_CPU as usize].set_sort_column_id(COL_CPU_F32 as _);
         // The memory usage display has been improved, so to make efficient sort,
         // we have to separate the display and the actual number.
-        columns[3].set_sort_column_id(7);
+        columns[COL_MEM as usize].set_sort_column_id(COL_MEM_U64 as _);
         // The disk I/O usage display has been improved, so to make efficient sort,
         // we have to separate the display and the actual number.
-        columns[4].set_sort_column_id(8);
+        columns[COL_DISK_READ as usize].set_sort_column_id(COL_DISK_READ_U64 as _);
+        columns[COL_DISK_WRITE as usize].set_sort_column_id(COL_DISK_WRITE_U64 as _);
+        // Same for all the other numerical columns.
+        columns[COL_THREADS as usize].set_sort_column_id(COL_THREADS_U32 as _);
+        columns[COL_NICE as usize].set_sort_column_id(COL_NICE_I32 as _);
+        columns[COL_START_TIME as usize].set_sort_column_id(COL_START_TIME_U64 as _);
+        columns[COL_VIRTUAL_MEM as usize].set_sort_column_id(COL_VIRTUAL_MEM_U64 as _);
+        columns[COL_PARENT_PID as usize].set_sort_column_id(COL_PARENT_PID_U32 as _);
+        columns[COL_SUBTREE_CPU as usize].set_sort_column_id(COL_SUBTREE_CPU_F32 as _);
+        columns[COL_SUBTREE_MEM as usize].set_sort_column_id(COL_SUBTREE_MEM_U64 as _);
 
-        filter_entry.connect_property_text_length_notify(move |_| {
-            filter_model.refilter();
-        });
+        // The subtree columns only make sense in tree mode.
+        columns[COL_SUBTREE_CPU as usize].set_visible(false);
+        columns[COL_SUBTREE_MEM as usize].set_visible(false);
+        columns::setup_column_chooser(&left_tree);
 
-        note.create_tab("Process list", &vertical_layout);
-
-        filter_button.connect_clicked(clone!(@weak filter_entry, @weak window => move |_| {
-            if filter_entry.get_visible() {
-                filter_entry.hide();
-            } else {
-                filter_entry.show_all();
-                window.set_focus(Some(&filter_entry));
+        let subtree_columns = [
+            columns[COL_SUBTREE_CPU as usize].clone(),
+            columns[COL_SUBTREE_MEM as usize].clone(),
+        ];
+        tree_button.connect_toggled(clone!(@weak tree_mode => move |button| {
+            let is_active = button.get_active();
+            tree_mode.set(is_active);
+            for column in &subtree_columns {
+                column.set_visible(is_active);
             }
         }));
 
+        group_combo.append(Some(get_group_by_id(None)), "No grouping");
+        for group in GroupBy::ALL {
+            group_combo.append(Some(group.get_id()), group.get_label());
+        }
+        group_combo.set_active_id(Some(get_group_by_id(None)));
+        // The processes are listed under their group so they cannot be displayed as a tree.
+        group_combo.connect_changed(clone!(@weak group_by, @weak tree_button => move |combo| {
+            let id = combo.get_active_id();
+            let group = GroupBy::ALL
+                .iter()
+                .cloned()
+                .find(|group| id.as_ref().map(|id| id.as_str()) == Some(group.get_id()));
+            group_by.set(group);
+            tree_button.set_sensitive(group.is_none());
+        }));
+
+        for button in filter_options.buttons() {
+            button.connect_toggled(
+                clone!(@weak filter_entry, @weak filter_error, @weak filter_query, @weak filter_model, @strong filter_options => move |_| {
+                    update_filter_query(&filter_entry, filter_options.get(), &filter_query, &filter_error);
+                    filter_model.refilter();
+                }),
+            );
+        }
+        filter_entry.connect_changed(
+            clone!(@weak filter_error, @weak filter_query => move |entry| {
+                update_filter_query(entry, filter_options.get(), &filter_query, &filter_error);
+                filter_model.refilter();
+            }),
+        );
+
+        note.create_tab("Process list", &vertical_layout);
+
+        filter_button.connect_clicked(
+            clone!(@weak filter_entry, @weak filter_error, @weak window => move |_| {
+                if filter_entry.get_visible() {
+                    filter_entry.hide();
+                    filter_error.hide();
+                } else {
+                    filter_entry.show_all();
+                    window.set_focus(Some(&filter_entry));
+                }
+            }),
+        );
+
         Procs {
             left_tree,
             scroll,
-            current_pid,
+            current_pids,
             kill_button,
+            pause_button,
+            resume_button,
+            signal_button,
             info_button,
+            tree_button,
             vertical_layout: vertical_layout
                 .downcast::<gtk::Box>()
                 .expect("downcast failed"),
-            list_store,
+            tree_store,
+            tree_mode,
+            group_combo,
+            group_by,
             columns,
             filter_entry,
             search_bar,
             filter_button,
+            status_bar,
         }
     }
 
@@ -235,6 +508,78 @@ impl Procs {
     }
 }
 
+/// The toggle buttons next to the filter entry.
+#[derive(Clone)]
+struct FilterOptions {
+    container: gtk::Box,
+    regex: gtk::ToggleButton,
+    case_sensitive: gtk::ToggleButton,
+    command_line: gtk::ToggleButton,
+}
+
+impl FilterOptions {
+    fn new() -> FilterOptions {
+        let options = FilterOptions {
+            container: gtk::Box::new(gtk::Orientation::Horizontal, 0),
+            regex: gtk::ToggleButton::new_with_label(".*"),
+            case_sensitive: gtk::ToggleButton::new_with_label("Aa"),
+            command_line: gtk::ToggleButton::new_with_label("cmd"),
+        };
+        options
+            .regex
+            .set_tooltip_text(Some("Use regular expressions"));
+        options.case_sensitive.set_tooltip_text(Some("Match case"));
+        options
+            .command_line
+            .set_tooltip_text(Some("Match the command line and the executable path too"));
+        options.container.get_style_context().add_class("linked");
+        for button in options.buttons() {
+            options.container.pack_start(button, false, false, 0);
+        }
+        options
+    }
+
+    fn buttons(&self) -> [&gtk::ToggleButton; 3] {
+        [&self.regex, &self.case_sensitive, &self.command_line]
+    }
+
+    fn get(&self) -> QueryOptions {
+        QueryOptions {
+            regex: self.regex.get_active(),
+            case_sensitive: self.case_sensitive.get_active(),
+            command_line: self.command_line.get_active(),
+        }
+    }
+}
+
+/// Parses the content of the filter entry and displays the error (if any) next to it.
+fn update_filter_query(
+    filter_entry: &gtk::Entry,
+    options: QueryOptions,
+    filter_query: &RefCell<Option<Query>>,
+    filter_error: &gtk::Label,
+) {
+    let text = filter_entry
+        .get_text()
+        .map(|t| t.to_string())
+        .unwrap_or_default();
+    match Query::parse(&text, options) {
+        Ok(query) => {
+            *filter_query.borrow_mut() = query;
+            filter_error.hide();
+        }
+        Err(e) => {
+            // We keep displaying all processes until the query is fixed.
+            *filter_query.borrow_mut() = None;
+            filter_error.set_markup(&format!(
+                "<span foreground=\"red\">{}</span>",
+                glib::markup_escape_text(&e.to_string())
+            ));
+            filter_error.show();
+        }
+    }
+}
+
 fn append_column(
     title: &str,
     v: &mut Vec<gtk::TreeViewColumn>,
@@ -258,36 +603,482 @@ fn append_column(
     column.set_min_width(10);
     column.pack_start(&renderer, true);
     column.add_attribute(&renderer, "text", id);
+    // The stopped processes are greyed out so they're not mistaken for running ones.
+    TreeViewColumnExt::set_cell_data_func(
+        &column,
+        &renderer,
+        Some(Box::new(move |_, renderer, model, iter| {
+            let is_stopped = model
+                .get_value(iter, COL_STATE as _)
+                .get::<String>()
+                .ok()
+                .and_then(|state| state)
+                .map(|state| state == STOPPED_STATE)
+                .unwrap_or(false);
+            renderer.set_sensitive(!is_stopped);
+            if let Some(renderer) = renderer.downcast_ref::<gtk::CellRendererText>() {
+                renderer.set_property_style(if is_stopped {
+                    pango::Style::Italic
+                } else {
+                    pango::Style::Normal
+                });
+                // The group rows have no PID.
+                if id == COL_PID as i32 && get_row_group(model, iter).is_some() {
+                    renderer.set_property_text(None);
+                }
+            }
+        })),
+    );
     column.set_clickable(true);
     column.set_sort_column_id(id);
     left_tree.append_column(&column);
     v.push(column);
 }
 
+/// Returns `false` for the processes which aren't displayed (kernel threads for example).
+pub fn is_displayable(pro: &Process) -> bool {
+    !pro.cmd().is_empty() && !pro.name().is_empty()
+}
+
 pub fn create_and_fill_model(
-    list_store: &gtk::ListStore,
-    pid: u32,
-    cmdline: &[String],
-    name: &str,
-    cpu: f32,
-    memory: u64,
-) {
-    if cmdline.is_empty() || name.is_empty() {
-        return;
+    tree_store: &gtk::TreeStore,
+    parent: Option<&gtk::TreeIter>,
+    pro: &Process,
+    refresh_interval: f64,
+) -> Option<gtk::TreeIter> {
+    if !is_displayable(pro) {
+        return None;
     }
-    list_store.insert_with_values(
+    let name = pro.name();
+    let iter = tree_store.insert_with_values(
+        parent,
         None,
-        &[0, 1, 2, 3, 4, 5, 6, 7, 8],
         &[
-            &pid,
+            COL_PID,
+            COL_NAME,
+            COL_START_TIME,
+            COL_NAME_LOWERCASE,
+            COL_START_TIME_U64,
+            COL_CMD,
+            COL_EXE,
+        ],
+        &[
+            &pro.pid().as_u32(),
+            &name,
+            &format_date(pro.start_time()),
+            &name.to_lowercase(),
+            &pro.start_time(),
+            &pro.cmd().join(" "),
+            &pro.exe().to_string_lossy().into_owned(),
+        ],
+    );
+    update_process_row(tree_store, &iter, pro, refresh_interval);
+    Some(iter)
+}
+
+/// Returns the number of bytes read and written per second by the process since the previous
+/// refresh, which happened `refresh_interval` seconds ago.
+pub fn get_disk_rates(pro: &Process, refresh_interval: f64) -> (u64, u64) {
+    let disk_usage = pro.disk_usage();
+    if refresh_interval <= 0. {
+        return (0, 0);
+    }
+    (
+        (disk_usage.read_bytes as f64 / refresh_interval) as u64,
+        (disk_usage.written_bytes as f64 / refresh_interval) as u64,
+    )
+}
+
+pub fn format_rate(rate: u64) -> String {
+    format!("{}/s", format_number(rate))
+}
+
+// Most processes don't do any I/O so we don't display anything for them.
+fn format_disk_rate(rate: u64) -> String {
+    if rate > 0 {
+        format_rate(rate)
+    } else {
+        String::new()
+    }
+}
+
+/// Updates all the columns which can change during the life of a process.
+pub fn update_process_row(
+    tree_store: &gtk::TreeStore,
+    iter: &gtk::TreeIter,
+    pro: &Process,
+    refresh_interval: f64,
+) {
+    let (disk_read, disk_write) = get_disk_rates(pro, refresh_interval);
+    let memory = pro.memory() * 1_000;
+    let virtual_memory = pro.virtual_memory() * 1_000;
+    let threads = get_thread_count(pro);
+    let nice = get_priority(pro.pid()).ok();
+    let parent = pro.parent().map(|p| p.as_u32());
+
+    tree_store.set(
+        iter,
+        &[
+            COL_CPU,
+            COL_MEM,
+            COL_DISK_READ,
+            COL_DISK_WRITE,
+            COL_USER,
+            COL_STATE,
+            COL_THREADS,
+            COL_NICE,
+            COL_VIRTUAL_MEM,
+            COL_PARENT_PID,
+            COL_CPU_F32,
+            COL_MEM_U64,
+            COL_DISK_READ_U64,
+            COL_DISK_WRITE_U64,
+            COL_THREADS_U32,
+            COL_NICE_I32,
+            COL_VIRTUAL_MEM_U64,
+            COL_PARENT_PID_U32,
+        ],
+        &[
+            &format!("{:.1}", pro.cpu_usage()),
+            &format_number(memory),
+            &format_disk_rate(disk_read),
+            &format_disk_rate(disk_write),
+            &get_process_user(pro),
+            &pro.status().to_string(),
+            &threads.map(|t| t.to_string()).unwrap_or_default(),
+            &nice.map(|n| n.to_string()).unwrap_or_default(),
+            &format_number(virtual_memory),
+            &parent.map(|p| p.to_string()).unwrap_or_default(),
+            &pro.cpu_usage(),
+            &memory,
+            &disk_read,
+            &disk_write,
+            &threads.unwrap_or(0),
+            &nice.unwrap_or(0),
+            &virtual_memory,
+            &parent.unwrap_or(0),
+        ],
+    );
+}
+
+pub fn create_group_row(tree_store: &gtk::TreeStore, group: &str) -> gtk::TreeIter {
+    tree_store.insert_with_values(None, None, &[COL_GROUP], &[&group])
+}
+
+/// Sets the values of the group row to the sum of the ones of its processes.
+pub fn update_group_row(tree_store: &gtk::TreeStore, iter: &gtk::TreeIter, group: &str) {
+    let mut count = 0;
+    let mut cpu = 0.;
+    let mut memory = 0;
+    let mut disk_read = 0;
+    let mut disk_write = 0;
+    let mut threads = 0;
+    let mut virtual_memory = 0;
+
+    if let Some(child) = tree_store.iter_children(Some(iter)) {
+        let get_u64 = |column: u32| {
+            tree_store
+                .get_value(&child, column as _)
+                .get::<u64>()
+                .unwrap_or(None)
+                .unwrap_or(0)
+        };
+        loop {
+            count += 1;
+            cpu += tree_store
+                .get_value(&child, COL_CPU_F32 as _)
+                .get::<f32>()
+                .unwrap_or(None)
+                .unwrap_or(0.);
+            memory += get_u64(COL_MEM_U64);
+            disk_read += get_u64(COL_DISK_READ_U64);
+            disk_write += get_u64(COL_DISK_WRITE_U64);
+            virtual_memory += get_u64(COL_VIRTUAL_MEM_U64);
+            threads += tree_store
+                .get_value(&child, COL_THREADS_U32 as _)
+                .get::<u32>()
+                .unwrap_or(None)
+                .unwrap_or(0);
+            if !tree_store.iter_next(&child) {
+                break;
+            }
+        }
+    }
+    let name = format!(
+        "{} ({} process{})",
+        group,
+        count,
+        if count == 1 { "" } else { "es" }
+    );
+
+    tree_store.set(
+        iter,
+        &[
+            COL_NAME,
+            COL_CPU,
+            COL_MEM,
+            COL_DISK_READ,
+            COL_DISK_WRITE,
+            COL_THREADS,
+            COL_VIRTUAL_MEM,
+            COL_NAME_LOWERCASE,
+            COL_CPU_F32,
+            COL_MEM_U64,
+            COL_DISK_READ_U64,
+            COL_DISK_WRITE_U64,
+            COL_THREADS_U32,
+            COL_VIRTUAL_MEM_U64,
+        ],
+        &[
             &name,
             &format!("{:.1}", cpu),
             &format_number(memory),
-            &String::new(),
+            &format_disk_rate(disk_read),
+            &format_disk_rate(disk_write),
+            &threads.to_string(),
+            &format_number(virtual_memory),
             &name.to_lowercase(),
             &cpu,
             &memory,
-            &0,
+            &disk_read,
+            &disk_write,
+            &threads,
+            &virtual_memory,
         ],
     );
 }
+
+/// Computes the CPU and memory usage of each row added to the ones of all its children and
+/// returns the total of the given level.
+pub fn update_subtree_totals(
+    tree_store: &gtk::TreeStore,
+    parent: Option<&gtk::TreeIter>,
+) -> (f32, u64) {
+    let mut total_cpu = 0.;
+    let mut total_memory = 0;
+
+    if let Some(iter) = tree_store.iter_children(parent) {
+        loop {
+            let (mut cpu, mut memory) = update_subtree_totals(tree_store, Some(&iter));
+            // The usage of a group is already the one of its processes.
+            if get_row_group(tree_store, &iter).is_none() {
+                cpu += tree_store
+                    .get_value(&iter, COL_CPU_F32 as _)
+                    .get::<f32>()
+                    .unwrap_or(None)
+                    .unwrap_or(0.);
+                memory += tree_store
+                    .get_value(&iter, COL_MEM_U64 as _)
+                    .get::<u64>()
+                    .unwrap_or(None)
+                    .unwrap_or(0);
+            }
+            tree_store.set(
+                &iter,
+                &[
+                    COL_SUBTREE_CPU,
+                    COL_SUBTREE_MEM,
+                    COL_SUBTREE_CPU_F32,
+                    COL_SUBTREE_MEM_U64,
+                ],
+                &[
+                    &format!("{:.1}", cpu),
+                    &format_number(memory),
+                    &cpu,
+                    &memory,
+                ],
+            );
+            total_cpu += cpu;
+            total_memory += memory;
+            if !tree_store.iter_next(&iter) {
+                break;
+            }
+        }
+    }
+    (total_cpu, total_memory)
+}
+
+/// Gives access to the values of a row of the process list to the filter query.
+struct ModelRow<'a> {
+    model: &'a gtk::TreeModel,
+    iter: &'a gtk::TreeIter,
+    /// The group rows only have the aggregated values of their processes: their other fields are
+    /// empty so they can only match through them or through their processes.
+    is_group: bool,
+}
+
+impl<'a> ModelRow<'a> {
+    fn get_string(&self, column: u32) -> String {
+        self.model
+            .get_value(self.iter, column as _)
+            .get::<String>()
+            .unwrap_or(None)
+            .unwrap_or_default()
+    }
+}
+
+impl<'a> Row for ModelRow<'a> {
+    fn get_number(&self, field: Field) -> Option<f64> {
+        let value = |column: u32| self.model.get_value(self.iter, column as _);
+        match field {
+            Field::Pid | Field::Nice | Field::ParentPid if self.is_group => None,
+            Field::Pid => value(COL_PID).get::<u32>().unwrap_or(None).map(f64::from),
+            Field::Cpu => value(COL_CPU_F32)
+                .get::<f32>()
+                .unwrap_or(None)
+                .map(f64::from),
+            Field::Memory => value(COL_MEM_U64)
+                .get::<u64>()
+                .unwrap_or(None)
+                .map(|v| v as f64),
+            Field::Disk => {
+                Some(self.get_number(Field::DiskRead)? + self.get_number(Field::DiskWrite)?)
+            }
+            Field::DiskRead => value(COL_DISK_READ_U64)
+                .get::<u64>()
+                .unwrap_or(None)
+                .map(|v| v as f64),
+            Field::DiskWrite => value(COL_DISK_WRITE_U64)
+                .get::<u64>()
+                .unwrap_or(None)
+                .map(|v| v as f64),
+            Field::Threads => value(COL_THREADS_U32)
+                .get::<u32>()
+                .unwrap_or(None)
+                .map(f64::from),
+            Field::Nice => value(COL_NICE_I32)
+                .get::<i32>()
+                .unwrap_or(None)
+                .map(f64::from),
+            Field::VirtualMemory => value(COL_VIRTUAL_MEM_U64)
+                .get::<u64>()
+                .unwrap_or(None)
+                .map(|v| v as f64),
+            Field::ParentPid => value(COL_PARENT_PID_U32)
+                .get::<u32>()
+                .unwrap_or(None)
+                .map(f64::from),
+            Field::Name | Field::User | Field::State | Field::Command | Field::Exe => None,
+        }
+    }
+
+    fn get_text(&self, field: Field) -> String {
+        match field {
+            Field::Pid
+            | Field::Nice
+            | Field::ParentPid
+            | Field::User
+            | Field::State
+            | Field::Command
+            | Field::Exe
+                if self.is_group =>
+            {
+                String::new()
+            }
+            Field::Pid => self
+                .model
+                .get_value(self.iter, COL_PID as _)
+                .get::<u32>()
+                .unwrap_or(None)
+                .map(|p| p.to_string())
+                .unwrap_or_default(),
+            Field::Name => self.get_string(COL_NAME),
+            Field::Cpu => self.get_string(COL_CPU),
+            Field::Memory => self.get_string(COL_MEM),
+            Field::Disk => format!(
+                "{} {}",
+                self.get_string(COL_DISK_READ),
+                self.get_string(COL_DISK_WRITE)
+            ),
+            Field::DiskRead => self.get_string(COL_DISK_READ),
+            Field::DiskWrite => self.get_string(COL_DISK_WRITE),
+            Field::User => self.get_string(COL_USER),
+            Field::State => self.get_string(COL_STATE),
+            Field::Threads => self.get_string(COL_THREADS),
+            Field::Nice => self.get_string(COL_NICE),
+            Field::VirtualMemory => self.get_string(COL_VIRTUAL_MEM),
+            Field::ParentPid => self.get_string(COL_PARENT_PID),
+            Field::Command => self.get_string(COL_CMD),
+            Field::Exe => self.get_string(COL_EXE),
+        }
+    }
+}
+
+fn is_row_or_child_matching(model: &gtk::TreeModel, iter: &gtk::TreeIter, query: &Query) -> bool {
+    let row = ModelRow {
+        model,
+        iter,
+        is_group: get_row_group(model, iter).is_some(),
+    };
+    if query.matches(&row) {
+        return true;
+    }
+    if let Some(child) = model.iter_children(Some(iter)) {
+        loop {
+            if is_row_or_child_matching(model, &child, query) {
+                return true;
+            }
+            if !model.iter_next(&child) {
+                break;
+            }
+        }
+    }
+    false
+}
+
+/// Selects back the rows of the given processes. It's needed because rows can be re-created
+/// when the process list is updated (when a process is moved in the tree for example).
+pub fn restore_selection(tree_view: &gtk::TreeView, pids: &HashMap<Pid, ProcessIdentity>) {
+    let model = match tree_view.get_model() {
+        Some(model) => model,
+        None => return,
+    };
+    let selection = tree_view.get_selection();
+    model.foreach(|model, path, iter| {
+        // If the PID has been reused by another process, we don't want to select it.
+        if let Some(identity) = get_row_identity(model, iter) {
+            if pids.get(&identity.pid) == Some(&identity) && !selection.path_is_selected(path) {
+                selection.select_path(path);
+            }
+        }
+        false
+    });
+}
+
+/// Returns the group displayed in the given row, `None` if it's a process row.
+pub fn get_row_group<M: IsA<gtk::TreeModel>>(model: &M, iter: &gtk::TreeIter) -> Option<String> {
+    model
+        .get_value(iter, COL_GROUP as _)
+        .get::<String>()
+        .unwrap_or(None)
+        .filter(|group| !group.is_empty())
+}
+
+/// Returns the identity of the process displayed in the given row (`None` for the group rows).
+pub fn get_row_identity<M: IsA<gtk::TreeModel>>(
+    model: &M,
+    iter: &gtk::TreeIter,
+) -> Option<ProcessIdentity> {
+    if get_row_group(model, iter).is_some() {
+        return None;
+    }
+    let pid = model
+        .get_value(iter, COL_PID as _)
+        .get::<u32>()
+        .unwrap_or(None)?;
+    let start_time = model
+        .get_value(iter, COL_START_TIME_U64 as _)
+        .get::<u64>()
+        .unwrap_or(None)?;
+    let exe = model
+        .get_value(iter, COL_EXE as _)
+        .get::<String>()
+        .unwrap_or(None)
+        .unwrap_or_default();
+
+    Some(ProcessIdentity {
+        pid: pid as Pid,
+        start_time,
+        exe,
+    })
+}
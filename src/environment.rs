@@ -0,0 +1,316 @@
+//! The "Environment" tab of the process dialog.
+
+use gdk;
+use glib;
+use gtk;
+use gtk::prelude::{
+    BoxExt, ButtonExt, CellLayoutExt, ContainerExt, DialogExt, EntryExt, GtkListStoreExt,
+    GtkListStoreExtManual, GtkWindowExt, LabelExt, ScrolledWindowExt, SearchEntryExt,
+    SpinButtonExt, ToggleButtonExt, TreeModelExt, TreeModelFilterExt, TreeViewColumnExt,
+    TreeViewExt, WidgetExt,
+};
+use sysinfo::Pid;
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use notebook::NoteBook;
+use procfs::get_environment;
+use utils::{get_main_window, save_to_file, StatusBar};
+
+/// Splits the `NAME=value` strings. The variables are sorted by name.
+fn parse_environment(environment: &[String]) -> BTreeMap<String, String> {
+    environment
+        .iter()
+        .filter_map(|var| {
+            let mut parts = var.splitn(2, '=');
+            let name = parts.next().filter(|name| !name.is_empty())?;
+            Some((name.to_owned(), parts.next().unwrap_or("").to_owned()))
+        })
+        .collect()
+}
+
+/// Quotes the value so it can be used in a POSIX shell.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Quotes the value the way `.env` files (as read by docker-compose or dotenv) expect it.
+fn dotenv_quote(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' | '$' | '`' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn create_button_with_tooltip(label: &str, tooltip: &str) -> gtk::Button {
+    let button = gtk::Button::new_with_label(label);
+    button.set_tooltip_text(Some(tooltip));
+    button
+}
+
+fn append_column(tree: &gtk::TreeView, title: &str, pos: i32) {
+    let column = gtk::TreeViewColumn::new();
+    let cell = gtk::CellRendererText::new();
+
+    column.set_title(title);
+    column.set_resizable(true);
+    column.pack_start(&cell, true);
+    column.add_attribute(&cell, "text", pos);
+    column.set_sort_column_id(pos);
+    tree.append_column(&column);
+}
+
+struct EnvironmentTab {
+    pid: Pid,
+    name: String,
+    /// The variables currently displayed, in the displayed order.
+    model: gtk::TreeModelSort,
+    variables: BTreeMap<String, String>,
+    status_bar: StatusBar,
+}
+
+impl EnvironmentTab {
+    fn get_displayed_variables(&self) -> Vec<(String, String)> {
+        let mut variables = Vec::new();
+
+        if let Some(iter) = self.model.get_iter_first() {
+            loop {
+                let get = |pos| {
+                    self.model
+                        .get_value(&iter, pos)
+                        .get::<String>()
+                        .unwrap_or(None)
+                        .unwrap_or_default()
+                };
+                variables.push((get(0), get(1)));
+                if !self.model.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+        variables
+    }
+
+    fn copy_as_export(&self) {
+        let variables = self.get_displayed_variables();
+        let text = variables
+            .iter()
+            .map(|(name, value)| format!("export {}={}\n", name, shell_quote(value)))
+            .collect::<String>();
+
+        gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD).set_text(&text);
+        self.status_bar.show_message(
+            gtk::MessageType::Info,
+            &format!("Copied {} variables to the clipboard", variables.len()),
+        );
+    }
+
+    fn save_as_dotenv(&self) {
+        let content = self
+            .get_displayed_variables()
+            .iter()
+            .map(|(name, value)| format!("{}={}\n", name, dotenv_quote(value)))
+            .collect::<String>();
+        match save_to_file(
+            get_main_window().as_ref(),
+            "Save the environment variables",
+            &format!("{}.env", self.name),
+            &content,
+        ) {
+            Some(Ok(path)) => self.status_bar.show_message(
+                gtk::MessageType::Info,
+                &format!("Saved to {}", path.display()),
+            ),
+            Some(Err(e)) => self.status_bar.show_message(gtk::MessageType::Error, &e),
+            None => {}
+        }
+    }
+
+    fn show_diff_dialog(&self) {
+        let dialog = gtk::Dialog::new_with_buttons(
+            Some(&format!("Compare the environment of {} with...", self.pid)),
+            get_main_window().as_ref(),
+            gtk::DialogFlags::DESTROY_WITH_PARENT,
+            &[("Close", gtk::ResponseType::Close)],
+        );
+        let pid_spin = gtk::SpinButton::new_with_range(1., f64::from(i32::MAX), 1.);
+        let compare_button = gtk::Button::new_with_label("Compare");
+        let show_identical = gtk::CheckButton::new_with_label("Show identical variables");
+        let summary = gtk::Label::new(None);
+        let list_store = gtk::ListStore::new(&[
+            glib::Type::String, // name
+            glib::Type::String, // value in this process
+            glib::Type::String, // value in the other process
+            glib::Type::String, // difference
+        ]);
+        let tree = gtk::TreeView::new_with_model(&list_store);
+        let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+        let top = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+
+        append_column(&tree, "name", 0);
+        append_column(&tree, &format!("value in {}", self.pid), 1);
+        append_column(&tree, "value in the other process", 2);
+        append_column(&tree, "difference", 3);
+
+        pid_spin.set_value(f64::from(self.pid));
+        top.pack_start(&gtk::Label::new(Some("PID:")), false, false, 0);
+        top.pack_start(&pid_spin, false, false, 0);
+        top.pack_start(&compare_button, false, false, 0);
+        top.pack_start(&show_identical, false, false, 0);
+        summary.set_halign(gtk::Align::Start);
+        scroll.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+        scroll.add(&tree);
+
+        let content_area = dialog.get_content_area();
+        content_area.set_border_width(10);
+        content_area.set_spacing(5);
+        content_area.pack_start(&top, false, false, 0);
+        content_area.pack_start(&summary, false, false, 0);
+        content_area.pack_start(&scroll, true, true, 0);
+
+        let variables = self.variables.clone();
+        let fill = clone!(@weak pid_spin, @weak show_identical, @weak summary, @weak list_store => move || {
+            let pid = pid_spin.get_value_as_int() as Pid;
+            list_store.clear();
+            let other = match get_environment(pid) {
+                Ok(other) => parse_environment(&other),
+                Err(e) => {
+                    summary.set_text(&format!("Cannot read the environment of {}: {}", pid, e));
+                    return;
+                }
+            };
+            let mut names = variables.keys().chain(other.keys()).collect::<Vec<_>>();
+            names.sort();
+            names.dedup();
+            let mut differences = 0;
+            for name in names {
+                let (ours, theirs) = (variables.get(name), other.get(name));
+                let difference = match (ours, theirs) {
+                    (Some(_), None) => "only here",
+                    (None, Some(_)) => "only in the other process",
+                    (Some(ours), Some(theirs)) if ours != theirs => "different",
+                    _ if show_identical.get_active() => "",
+                    _ => continue,
+                };
+                if !difference.is_empty() {
+                    differences += 1;
+                }
+                list_store.insert_with_values(
+                    None,
+                    &[0, 1, 2, 3],
+                    &[
+                        name,
+                        &ours.map(String::as_str).unwrap_or(""),
+                        &theirs.map(String::as_str).unwrap_or(""),
+                        &difference,
+                    ],
+                );
+            }
+            summary.set_text(&format!("{} differences", differences));
+        });
+        let fill = Rc::new(fill);
+        compare_button.connect_clicked(clone!(@strong fill => move |_| fill()));
+        show_identical.connect_toggled(clone!(@strong fill => move |_| fill()));
+        pid_spin.connect_activate(move |_| fill());
+        dialog.connect_response(|dialog, _| dialog.close());
+
+        dialog.set_default_size(600, 400);
+        dialog.show_all();
+    }
+}
+
+/// Adds the "Environment" tab to the process dialog. Nothing is added if the process has no
+/// environment variables (or if we're not allowed to read them).
+pub fn create_environment_tab(
+    pid: Pid,
+    name: &str,
+    environment: &[String],
+    notebook: &mut NoteBook,
+) {
+    let variables = parse_environment(environment);
+    if variables.is_empty() {
+        return;
+    }
+    let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 5);
+    let filter_entry = gtk::SearchEntry::new();
+    let list_store = gtk::ListStore::new(&[glib::Type::String, glib::Type::String]);
+    let filter_model = gtk::TreeModelFilter::new(&list_store, None);
+    let sort_model = gtk::TreeModelSort::new(&filter_model);
+    let tree = gtk::TreeView::new_with_model(&sort_model);
+    let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+    let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    let copy_button = create_button_with_tooltip(
+        "Copy as export lines",
+        "Copy the displayed variables as shell `export` commands",
+    );
+    let save_button = create_button_with_tooltip(
+        "Save as .env file",
+        "Save the displayed variables in the .env format",
+    );
+    let diff_button = create_button_with_tooltip(
+        "Compare with...",
+        "Compare the environment with the one of another process",
+    );
+    let status_bar = StatusBar::new();
+
+    for (name, value) in &variables {
+        list_store.insert_with_values(None, &[0, 1], &[name, value]);
+    }
+    filter_model.set_visible_func(
+        clone!(@weak filter_entry => @default-return true, move |model, iter| {
+            let text = match filter_entry.get_text() {
+                Some(text) if !text.is_empty() => text.to_lowercase(),
+                _ => return true,
+            };
+            (0..2).any(|pos| {
+                model
+                    .get_value(iter, pos)
+                    .get::<String>()
+                    .unwrap_or(None)
+                    .map(|value| value.to_lowercase().contains(&text))
+                    .unwrap_or(false)
+            })
+        }),
+    );
+    filter_entry.connect_search_changed(clone!(@weak filter_model => move |_| {
+        filter_model.refilter();
+    }));
+    append_column(&tree, "name", 0);
+    append_column(&tree, "value", 1);
+
+    filter_entry.set_placeholder_text(Some("Filter"));
+    scroll.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+    scroll.add(&tree);
+    buttons.pack_start(&copy_button, true, true, 0);
+    buttons.pack_start(&save_button, true, true, 0);
+    buttons.pack_start(&diff_button, true, true, 0);
+    vertical_layout.set_margin_top(5);
+    vertical_layout.pack_start(&filter_entry, false, true, 0);
+    vertical_layout.pack_start(&scroll, true, true, 0);
+    vertical_layout.pack_start(&buttons, false, true, 0);
+    vertical_layout.pack_start(&status_bar.info_bar, false, true, 0);
+    notebook.create_tab("Environment", &vertical_layout);
+
+    let tab = Rc::new(EnvironmentTab {
+        pid,
+        name: name.to_owned(),
+        model: sort_model,
+        variables,
+        status_bar,
+    });
+    copy_button.connect_clicked(clone!(@strong tab => move |_| tab.copy_as_export()));
+    save_button.connect_clicked(clone!(@strong tab => move |_| tab.save_as_dotenv()));
+    diff_button.connect_clicked(move |_| tab.show_diff_dialog());
+}
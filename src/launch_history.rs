@@ -0,0 +1,126 @@
+//! The commands run from the "Launch new executable" dialog and the saved launch profiles.
+
+use gio;
+use glib::ToVariant;
+
+use std::fs;
+use std::path::PathBuf;
+
+use launcher::{LaunchOptions, ProcessLimits};
+use settings::{show_error_dialog, Settings};
+
+/// The maximum number of commands kept in the history.
+const MAX_HISTORY_LEN: usize = 50;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LaunchProfile {
+    pub name: String,
+    pub command_line: String,
+    pub working_directory: Option<PathBuf>,
+    pub environment: Vec<(String, String)>,
+    pub capture_output: bool,
+}
+
+impl LaunchProfile {
+    pub fn to_options(&self) -> LaunchOptions {
+        LaunchOptions {
+            command_line: self.command_line.clone(),
+            working_directory: self.working_directory.clone(),
+            environment: self.environment.clone(),
+            capture_output: self.capture_output,
+            limits: ProcessLimits::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct LaunchHistory {
+    // The most recent command first.
+    pub commands: Vec<String>,
+    // Sorted by name.
+    pub profiles: Vec<LaunchProfile>,
+}
+
+impl LaunchHistory {
+    fn get_file_path() -> PathBuf {
+        Settings::get_settings_file_path().with_file_name("launch_history.toml")
+    }
+
+    pub fn load() -> LaunchHistory {
+        let path = Self::get_file_path();
+        if !path.is_file() {
+            return LaunchHistory::default();
+        }
+        match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| toml::from_str(&content).map_err(|e| e.to_string()))
+        {
+            Ok(history) => history,
+            Err(e) => {
+                show_error_dialog(
+                    false,
+                    &format!("Error while opening '{}': {}", path.display(), e),
+                );
+                LaunchHistory::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::get_file_path();
+        if let Some(parent_dir) = path.parent() {
+            fs::create_dir_all(parent_dir).map_err(|e| {
+                format!(
+                    "Error while trying to create '{}': {}",
+                    parent_dir.display(),
+                    e
+                )
+            })?;
+        }
+        let output = toml::to_string_pretty(self)
+            .map_err(|e| format!("Error while trying to save launch history: {}", e))?;
+        fs::write(&path, output)
+            .map_err(|e| format!("Error while trying to save '{}': {}", path.display(), e))
+    }
+
+    /// Moves the command to the top of the history.
+    pub fn add_command(&mut self, command_line: &str) {
+        self.commands.retain(|command| command != command_line);
+        self.commands.insert(0, command_line.to_owned());
+        self.commands.truncate(MAX_HISTORY_LEN);
+    }
+
+    pub fn get_profile(&self, name: &str) -> Option<&LaunchProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Adds the profile, replacing the one with the same name if any.
+    pub fn set_profile(&mut self, profile: LaunchProfile) {
+        self.remove_profile(&profile.name);
+        self.profiles.push(profile);
+        self.profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    pub fn remove_profile(&mut self, name: &str) {
+        self.profiles.retain(|profile| profile.name != name);
+    }
+
+    /// Fills the "Run launch profile" menu of the application menu.
+    pub fn fill_profiles_menu(&self, menu: &gio::Menu) {
+        menu.remove_all();
+        if self.profiles.is_empty() {
+            // No action so it's displayed as disabled.
+            menu.append(Some("No saved profile"), None);
+        }
+        for profile in &self.profiles {
+            let item = gio::MenuItem::new(Some(&profile.name), None);
+            item.set_action_and_target_value(
+                Some("app.run-profile"),
+                Some(&profile.name.to_variant()),
+            );
+            menu.append_item(&item);
+        }
+    }
+}
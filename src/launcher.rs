@@ -0,0 +1,1144 @@
+//! Starts new processes and keeps track of them.
+
+use gio;
+use glib::{self, Cast, IsA};
+use gtk;
+use gtk::prelude::{
+    BoxExt, ButtonBoxExt, ButtonExt, ComboBoxExt, ComboBoxTextExt, ContainerExt, DialogExt,
+    EditableSignals, EntryCompletionExt, EntryExt, FileChooserExt, GridExt, GtkListStoreExtManual,
+    GtkWindowExt, LabelExt, ScrolledWindowExt, TextBufferExt, TextTagExt, TextTagTableExt,
+    TextViewExt, ToggleButtonExt, WidgetExt,
+};
+use libc::{self, c_int};
+use sysinfo::{self, Pid, ProcessExt, SystemExt};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+#[cfg(target_os = "linux")]
+use std::mem;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::rc::Rc;
+use std::thread;
+
+use launch_history::{LaunchHistory, LaunchProfile};
+use procfs::create_cgroup;
+#[cfg(unix)]
+use scheduling::set_priority;
+use scheduling::{get_cpu_count, MAX_NICE, MIN_NICE};
+use shell_words;
+#[cfg(unix)]
+use signals::get_signal_name;
+use utils::{get_now, ProcessIdentity};
+
+struct LaunchedProcess {
+    child: Child,
+    /// In seconds since the epoch.
+    launched_at: u64,
+    /// Found in the process list once it has been refreshed after the launch.
+    identity: Option<ProcessIdentity>,
+}
+
+/// The processes started by process-viewer. We keep them to be able to get their exit status
+/// (which also prevents them from staying around as zombies once they're done).
+#[derive(Default)]
+pub struct LaunchedProcesses {
+    running: Vec<LaunchedProcess>,
+    /// The PIDs can be reused once the processes are reaped so they're identified by their
+    /// `ProcessIdentity`.
+    exit_statuses: HashMap<ProcessIdentity, ExitStatus>,
+    /// The cgroups created for the running processes.
+    cgroups: HashMap<Pid, PathBuf>,
+    cgroup_count: u32,
+}
+
+impl LaunchedProcesses {
+    fn add(&mut self, child: Child) -> Pid {
+        let pid = child.id() as Pid;
+        self.running.push(LaunchedProcess {
+            child,
+            launched_at: get_now(),
+            identity: None,
+        });
+        pid
+    }
+
+    fn get_new_cgroup_name(&mut self) -> String {
+        self.cgroup_count += 1;
+        format!(
+            "process-viewer-{}-{}",
+            ::std::process::id(),
+            self.cgroup_count
+        )
+    }
+
+    /// Collects the exit status of the processes which are done.
+    pub fn check_exited(&mut self, sys: &sysinfo::System) {
+        let exit_statuses = &mut self.exit_statuses;
+        let cgroups = &mut self.cgroups;
+        self.running.retain_mut(|launched| {
+            let pid = launched.child.id() as Pid;
+            // Until it's reaped, the PID cannot be reused. The process list can still be older
+            // than the launch though, so the process has to be started after it.
+            if launched.identity.is_none() {
+                launched.identity = sys
+                    .get_process(pid)
+                    .filter(|p| p.start_time() + 1 >= launched.launched_at)
+                    .map(ProcessIdentity::new);
+            }
+            let status = match launched.child.try_wait() {
+                Ok(Some(status)) => Some(status),
+                Ok(None) => return true,
+                // We won't be able to get its exit status so no need to keep it.
+                Err(_) => None,
+            };
+            if let (Some(identity), Some(status)) = (launched.identity.take(), status) {
+                exit_statuses.insert(identity, status);
+            }
+            // It fails if some of its children are still running, in which case we leave it.
+            if let Some(directory) = cgroups.remove(&pid) {
+                let _ = fs::remove_dir(directory);
+            }
+            false
+        });
+    }
+
+    /// Returns the exit status of the process if it has been started by process-viewer. It can
+    /// only be retrieved once.
+    pub fn take_exit_status(&mut self, identity: &ProcessIdentity) -> Option<ExitStatus> {
+        self.exit_statuses.remove(identity)
+    }
+}
+
+/// Returns a description of the exit status such as "exited with code 1".
+pub fn describe_exit_status(status: &ExitStatus) -> String {
+    if let Some(code) = status.code() {
+        return format!("exited with code {}", code);
+    }
+    #[cfg(unix)]
+    {
+        if let Some(signal) = status.signal() {
+            return format!("killed by {}", get_signal_name(signal));
+        }
+    }
+    "unknown".to_owned()
+}
+
+/// Limits and scheduling options applied to the new process before it starts.
+#[derive(Default)]
+pub struct ProcessLimits {
+    pub nice: Option<c_int>,
+    /// For each processor, whether the process is allowed to run on it.
+    pub cpu_affinity: Option<Vec<bool>>,
+    /// In bytes.
+    pub max_address_space: Option<u64>,
+    pub max_open_files: Option<u64>,
+    /// In seconds.
+    pub max_cpu_time: Option<u64>,
+    /// If set, the process is started in a new cgroup with these limits.
+    pub cgroup: Option<CgroupLimits>,
+}
+
+impl ProcessLimits {
+    #[cfg(windows)]
+    fn is_empty(&self) -> bool {
+        self.nice.is_none()
+            && self.cpu_affinity.is_none()
+            && self.max_address_space.is_none()
+            && self.max_open_files.is_none()
+            && self.max_cpu_time.is_none()
+            && self.cgroup.is_none()
+    }
+}
+
+pub struct CgroupLimits {
+    /// In bytes.
+    pub memory_max: Option<u64>,
+    /// In number of CPUs.
+    pub cpu_max: Option<f64>,
+}
+
+/// Moves the current process into the cgroup whose `cgroup.procs` file is `fd`. It's called
+/// between `fork` and `exec` so it must not allocate.
+#[cfg(unix)]
+fn join_cgroup(fd: RawFd) -> io::Result<()> {
+    // "0" means the process writing into the file.
+    if unsafe { libc::write(fd, b"0".as_ptr() as *const libc::c_void, 1) } != 1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn build_command<'a>(
+    c: &'a mut Command,
+    limits: &ProcessLimits,
+    cgroup_procs: Option<&fs::File>,
+) -> &'a mut Command {
+    let cgroup_procs = cgroup_procs.map(AsRawFd::as_raw_fd);
+    let nice = limits.nice;
+    #[cfg(target_os = "linux")]
+    let cpu_set = limits.cpu_affinity.as_ref().map(|cpus| unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for (cpu, _) in cpus.iter().enumerate().filter(|(_, allowed)| **allowed) {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        set
+    });
+    let rlimits = [
+        (libc::RLIMIT_AS, limits.max_address_space),
+        (libc::RLIMIT_NOFILE, limits.max_open_files),
+        (libc::RLIMIT_CPU, limits.max_cpu_time),
+    ];
+
+    unsafe {
+        c.pre_exec(move || {
+            libc::setsid();
+            // The cgroup is joined first so everything the process does is accounted to it.
+            if let Some(fd) = cgroup_procs {
+                join_cgroup(fd)?;
+            }
+            if let Some(nice) = nice {
+                set_priority(0, nice)?;
+            }
+            // `set_affinity` isn't used because some of its errors allocate.
+            #[cfg(target_os = "linux")]
+            {
+                if let Some(ref set) = cpu_set {
+                    if libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), set) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+            }
+            for &(resource, value) in &rlimits {
+                if let Some(value) = value {
+                    let limit = libc::rlimit {
+                        rlim_cur: value as libc::rlim_t,
+                        rlim_max: value as libc::rlim_t,
+                    };
+                    if libc::setrlimit(resource, &limit) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(windows)]
+fn build_command<'a>(
+    c: &'a mut Command,
+    _limits: &ProcessLimits,
+    _cgroup_procs: Option<&fs::File>,
+) -> &'a mut Command {
+    c
+}
+
+/// Creates the cgroup and opens its `cgroup.procs` file, which the new process writes into to
+/// join it.
+fn prepare_cgroup(
+    limits: &CgroupLimits,
+    launched: &mut LaunchedProcesses,
+) -> io::Result<(PathBuf, fs::File)> {
+    let directory = create_cgroup(
+        &launched.get_new_cgroup_name(),
+        limits.memory_max,
+        limits.cpu_max,
+    )?;
+    match fs::OpenOptions::new()
+        .write(true)
+        .open(directory.join("cgroup.procs"))
+    {
+        Ok(file) => Ok((directory, file)),
+        Err(e) => {
+            let _ = fs::remove_dir(&directory);
+            Err(e)
+        }
+    }
+}
+
+/// How a new process should be started.
+pub struct LaunchOptions {
+    pub command_line: String,
+    /// If `None`, the process is started in our own working directory.
+    pub working_directory: Option<PathBuf>,
+    /// Added to the environment inherited from process-viewer.
+    pub environment: Vec<(String, String)>,
+    /// If `true`, stdout and stderr are displayed in a log window.
+    pub capture_output: bool,
+    pub limits: ProcessLimits,
+}
+
+/// Parses one `NAME=value` variable per line. Empty lines are ignored.
+fn parse_environment_lines(text: &str) -> Result<Vec<(String, String)>, String> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut parts = line.splitn(2, '=');
+            match (parts.next().map(str::trim), parts.next()) {
+                (Some(name), Some(value)) if !name.is_empty() => {
+                    Ok((name.to_owned(), value.to_owned()))
+                }
+                _ => Err(format!(
+                    "Invalid environment variable \"{}\": expected NAME=value",
+                    line
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Starts the command and returns its PID.
+pub fn start_detached_process(
+    options: &LaunchOptions,
+    launched: &mut LaunchedProcesses,
+) -> Result<Pid, String> {
+    let args = shell_words::split(&options.command_line, &options.environment)
+        .map_err(|e| format!("Invalid command: {}", e))?;
+    let command = match args.first() {
+        Some(command) => command.clone(),
+        None => return Err("No command to start".to_owned()),
+    };
+    let mut cmd = Command::new(&command);
+
+    cmd.args(&args[1..])
+        .envs(
+            options
+                .environment
+                .iter()
+                .map(|(name, value)| (name, value)),
+        )
+        .stdin(Stdio::null());
+    if let Some(ref directory) = options.working_directory {
+        if !directory.is_dir() {
+            return Err(format!(
+                "Failed to start '{}': {} is not a directory",
+                &command,
+                directory.display()
+            ));
+        }
+        cmd.current_dir(directory);
+    }
+    if options.capture_output {
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    } else {
+        cmd.stdout(Stdio::null()).stderr(Stdio::null());
+    }
+    #[cfg(windows)]
+    {
+        if !options.limits.is_empty() {
+            return Err(format!(
+                "Failed to start '{}': limits are not supported on this platform",
+                &command
+            ));
+        }
+    }
+    #[cfg(all(unix, not(target_os = "linux")))]
+    {
+        if options.limits.cpu_affinity.is_some() {
+            return Err(format!(
+                "Failed to start '{}': the CPU affinity is not supported on this platform",
+                &command
+            ));
+        }
+    }
+    let cgroup = match options.limits.cgroup {
+        Some(ref limits) => Some(
+            prepare_cgroup(limits, launched)
+                .map_err(|e| format!("Failed to create a cgroup for '{}': {}", &command, e))?,
+        ),
+        None => None,
+    };
+    let spawned = build_command(
+        &mut cmd,
+        &options.limits,
+        cgroup.as_ref().map(|(_, procs)| procs),
+    )
+    .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            if let Some((directory, _)) = cgroup {
+                let _ = fs::remove_dir(directory);
+            }
+            return Err(format!("Failed to start '{}': {}", &command, e));
+        }
+    };
+    let pid = child.id() as Pid;
+    if options.capture_output {
+        show_output_window(pid, &command, child.stdout.take(), child.stderr.take());
+    }
+    if let Some((directory, _)) = cgroup {
+        launched.cgroups.insert(pid, directory);
+    }
+    launched.add(child);
+    Ok(pid)
+}
+
+/// Sends the lines read from `reader` to the output window. `None` is sent once the stream is
+/// closed.
+fn forward_lines<R: Read + Send + 'static>(
+    reader: R,
+    is_stderr: bool,
+    sender: glib::Sender<Option<(bool, String)>>,
+) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                // If the window has been closed, we keep reading anyway so the process doesn't
+                // get stuck on a full pipe.
+                Ok(_) => {
+                    let _ = sender.send(Some((
+                        is_stderr,
+                        String::from_utf8_lossy(&line).into_owned(),
+                    )));
+                }
+            }
+        }
+        let _ = sender.send(None);
+    });
+}
+
+/// Displays what the process writes on its stdout and stderr (in red) as it comes.
+fn show_output_window<O, E>(pid: Pid, command: &str, stdout: Option<O>, stderr: Option<E>)
+where
+    O: Read + Send + 'static,
+    E: Read + Send + 'static,
+{
+    let window = gtk::Window::new(gtk::WindowType::Toplevel);
+    let tag_table = gtk::TextTagTable::new();
+    let stderr_tag = gtk::TextTag::new(Some("stderr"));
+    stderr_tag.set_property_foreground(Some("red"));
+    tag_table.add(&stderr_tag);
+    let buffer = gtk::TextBuffer::new(Some(&tag_table));
+    let text_view = gtk::TextView::new_with_buffer(&buffer);
+    let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+    let status = gtk::Label::new(Some("Running"));
+    let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 5);
+    let end_mark = buffer.create_mark(Some("end"), &buffer.get_end_iter(), false);
+
+    text_view.set_editable(false);
+    text_view.set_property_monospace(true);
+    scroll.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+    scroll.add(&text_view);
+    status.set_halign(gtk::Align::Start);
+    vertical_layout.set_border_width(5);
+    vertical_layout.pack_start(&scroll, true, true, 0);
+    vertical_layout.pack_start(&status, false, true, 0);
+    window.add(&vertical_layout);
+    window.set_title(&format!("Output of {} (PID {})", command, pid));
+    window.set_default_size(600, 400);
+    window.show_all();
+
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    let mut open_streams = 0;
+    if let Some(stdout) = stdout {
+        forward_lines(stdout, false, sender.clone());
+        open_streams += 1;
+    }
+    if let Some(stderr) = stderr {
+        forward_lines(stderr, true, sender);
+        open_streams += 1;
+    }
+    receiver.attach(None,
+        clone!(@weak buffer, @weak text_view, @weak status => @default-return glib::Continue(false), move |line| {
+            match line {
+                Some((is_stderr, text)) => {
+                    let start = buffer.get_end_iter().get_offset();
+                    buffer.insert(&mut buffer.get_end_iter(), &text);
+                    if is_stderr {
+                        buffer.apply_tag_by_name(
+                            "stderr",
+                            &buffer.get_iter_at_offset(start),
+                            &buffer.get_end_iter(),
+                        );
+                    }
+                    if let Some(ref end_mark) = end_mark {
+                        text_view.scroll_mark_onscreen(end_mark);
+                    }
+                }
+                None => {
+                    open_streams -= 1;
+                    if open_streams == 0 {
+                        status.set_text("The process closed its output");
+                        return glib::Continue(false);
+                    }
+                }
+            }
+            glib::Continue(true)
+        }),
+    );
+}
+
+fn show_result_dialog<T: IsA<gtk::Window>>(window: &T, result: Result<Pid, String>) {
+    let (message_type, text) = match result {
+        Ok(pid) => (
+            gtk::MessageType::Info,
+            format!("The command started successfully (PID {})", pid),
+        ),
+        Err(e) => (gtk::MessageType::Error, e),
+    };
+    let m = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::DESTROY_WITH_PARENT,
+        message_type,
+        gtk::ButtonsType::Ok,
+        &text,
+    );
+    m.set_modal(true);
+    m.connect_response(|dialog, response| {
+        if response == gtk::ResponseType::DeleteEvent
+            || response == gtk::ResponseType::Close
+            || response == gtk::ResponseType::Ok
+        {
+            dialog.close();
+        }
+    });
+    m.show_all();
+}
+
+fn add_row<W: IsA<gtk::Widget>>(grid: &gtk::Grid, row: i32, label: &str, widget: &W) {
+    let label = gtk::Label::new(Some(label));
+    label.set_halign(gtk::Align::End);
+    label.set_valign(gtk::Align::Start);
+    grid.attach(&label, 0, row, 1, 1);
+    grid.attach(widget, 1, row, 1, 1);
+}
+
+/// Parses the content of the entry. An empty entry means "no limit".
+fn parse_optional<T, F>(entry: &gtk::Entry, name: &str, parse: F) -> Result<Option<T>, String>
+where
+    F: FnOnce(&str) -> Option<T>,
+{
+    let text = entry
+        .get_text()
+        .map(|text| text.trim().to_owned())
+        .unwrap_or_default();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    parse(&text)
+        .map(Some)
+        .ok_or_else(|| format!("Invalid {}: \"{}\"", name, text))
+}
+
+/// Parses lists of processors like `0-3,6`. All of them must be lower than `cpu_count`.
+fn parse_cpu_list(text: &str, cpu_count: usize) -> Option<Vec<bool>> {
+    let mut cpus = vec![false; cpu_count];
+
+    for part in text.split(',') {
+        let mut bounds = part.splitn(2, '-');
+        let start = bounds.next()?.trim().parse::<usize>().ok()?;
+        let end = match bounds.next() {
+            Some(end) => end.trim().parse::<usize>().ok()?,
+            None => start,
+        };
+        if start > end || end >= cpus.len() {
+            return None;
+        }
+        for allowed in &mut cpus[start..=end] {
+            *allowed = true;
+        }
+    }
+    Some(cpus)
+}
+
+/// Parses sizes like `512M` or `1.5GiB`. Like `ulimit` and systemd, the units are powers of 1024.
+fn parse_size(text: &str) -> Option<u64> {
+    let lower = text.trim().to_lowercase();
+    let number = lower.trim_end_matches("ib").trim_end_matches('b');
+    let (number, shift) = match number.chars().last() {
+        Some('k') => (&number[..number.len() - 1], 10),
+        Some('m') => (&number[..number.len() - 1], 20),
+        Some('g') => (&number[..number.len() - 1], 30),
+        Some('t') => (&number[..number.len() - 1], 40),
+        _ => (number, 0),
+    };
+    let size = number.trim().parse::<f64>().ok().filter(|n| *n >= 0.)? * (1u64 << shift) as f64;
+
+    if size < u64::MAX as f64 {
+        Some(size as u64)
+    } else {
+        None
+    }
+}
+
+fn create_entry(placeholder: &str) -> gtk::Entry {
+    let entry = gtk::Entry::new();
+    entry.set_placeholder_text(Some(placeholder));
+    entry.set_hexpand(true);
+    entry
+}
+
+/// The widgets of the "Limits" section of the launch dialog.
+struct LimitsWidgets {
+    nice: gtk::Entry,
+    cpu_affinity: gtk::Entry,
+    max_address_space: gtk::Entry,
+    max_open_files: gtk::Entry,
+    max_cpu_time: gtk::Entry,
+    use_cgroup: gtk::CheckButton,
+    cgroup_memory_max: gtk::Entry,
+    cgroup_cpu_max: gtk::Entry,
+}
+
+impl LimitsWidgets {
+    fn new() -> (LimitsWidgets, gtk::Expander) {
+        let widgets = LimitsWidgets {
+            nice: create_entry(&format!("inherited ({} to {})", MIN_NICE, MAX_NICE)),
+            cpu_affinity: create_entry(&format!(
+                "all, or a list like 0-3,6 (0 to {})",
+                get_cpu_count() - 1
+            )),
+            max_address_space: create_entry("unlimited, or a size like 512M (1K = 1024 bytes)"),
+            max_open_files: create_entry("inherited"),
+            max_cpu_time: create_entry("unlimited, in seconds"),
+            use_cgroup: gtk::CheckButton::new_with_label("Run in a new cgroup"),
+            cgroup_memory_max: create_entry("unlimited, or a size like 1G (1K = 1024 bytes)"),
+            cgroup_cpu_max: create_entry("unlimited, or a number of CPUs like 1.5"),
+        };
+        let expander = gtk::Expander::new(Some("Limits"));
+        let grid = gtk::Grid::new();
+
+        grid.set_row_spacing(5);
+        grid.set_column_spacing(5);
+        grid.set_margin_top(5);
+        add_row(&grid, 0, "Nice value:", &widgets.nice);
+        add_row(&grid, 1, "CPU affinity:", &widgets.cpu_affinity);
+        add_row(&grid, 2, "Max address space:", &widgets.max_address_space);
+        add_row(&grid, 3, "Max open files:", &widgets.max_open_files);
+        add_row(&grid, 4, "Max CPU time:", &widgets.max_cpu_time);
+        grid.attach(&widgets.use_cgroup, 1, 5, 1, 1);
+        add_row(&grid, 6, "Cgroup memory max:", &widgets.cgroup_memory_max);
+        add_row(&grid, 7, "Cgroup CPU max:", &widgets.cgroup_cpu_max);
+        expander.add(&grid);
+
+        widgets.cgroup_memory_max.set_sensitive(false);
+        widgets.cgroup_cpu_max.set_sensitive(false);
+        widgets.use_cgroup.connect_toggled(
+            clone!(@weak widgets.cgroup_memory_max as memory_max, @weak widgets.cgroup_cpu_max as cpu_max => move |use_cgroup| {
+                memory_max.set_sensitive(use_cgroup.get_active());
+                cpu_max.set_sensitive(use_cgroup.get_active());
+            }),
+        );
+        (widgets, expander)
+    }
+
+    fn get_limits(&self) -> Result<ProcessLimits, String> {
+        let cgroup = if self.use_cgroup.get_active() {
+            Some(CgroupLimits {
+                memory_max: parse_optional(
+                    &self.cgroup_memory_max,
+                    "cgroup memory max",
+                    parse_size,
+                )?,
+                cpu_max: parse_optional(&self.cgroup_cpu_max, "cgroup CPU max", |text| {
+                    text.parse::<f64>().ok().filter(|n| *n > 0.)
+                })?,
+            })
+        } else {
+            None
+        };
+        let cpu_affinity = parse_optional(&self.cpu_affinity, "CPU affinity", |text| {
+            parse_cpu_list(text, get_cpu_count())
+        })?;
+        if cfg!(not(target_os = "linux")) && cpu_affinity.is_some() {
+            return Err("The CPU affinity is not supported on this platform".to_owned());
+        }
+        Ok(ProcessLimits {
+            nice: parse_optional(&self.nice, "nice value", |text| {
+                text.parse::<c_int>()
+                    .ok()
+                    .filter(|n| *n >= MIN_NICE && *n <= MAX_NICE)
+            })?,
+            cpu_affinity,
+            max_address_space: parse_optional(
+                &self.max_address_space,
+                "max address space",
+                parse_size,
+            )?,
+            max_open_files: parse_optional(&self.max_open_files, "max open files", |text| {
+                text.parse().ok()
+            })?,
+            max_cpu_time: parse_optional(&self.max_cpu_time, "max CPU time", |text| {
+                text.parse().ok()
+            })?,
+            cgroup,
+        })
+    }
+}
+
+fn add_to_history(history: &RefCell<LaunchHistory>, command_line: &str) {
+    let mut history = history.borrow_mut();
+    history.add_command(command_line);
+    if let Err(e) = history.save() {
+        eprintln!("<launcher::add_to_history> {}", e);
+    }
+}
+
+/// Starts the process described by the profile, without showing the launch dialog.
+pub fn run_profile(
+    window: &gtk::ApplicationWindow,
+    name: &str,
+    launched_processes: &RefCell<LaunchedProcesses>,
+    history: &RefCell<LaunchHistory>,
+) {
+    let options = match history.borrow().get_profile(name) {
+        Some(profile) => profile.to_options(),
+        None => {
+            return show_result_dialog(
+                window,
+                Err(format!("The launch profile \"{}\" doesn't exist", name)),
+            )
+        }
+    };
+    let result = start_detached_process(&options, &mut launched_processes.borrow_mut());
+    if result.is_ok() {
+        add_to_history(history, &options.command_line);
+    }
+    show_result_dialog(window, result);
+}
+
+fn fill_profiles_combo(combo: &gtk::ComboBoxText, history: &LaunchHistory) {
+    combo.remove_all();
+    for profile in &history.profiles {
+        combo.append(Some(&profile.name), &profile.name);
+    }
+}
+
+/// The widgets of the "Launch new executable" dialog.
+struct LaunchDialog {
+    dialog: gtk::Dialog,
+    profiles: gtk::ComboBoxText,
+    input: gtk::Entry,
+    /// Shows how the command line is split into arguments.
+    preview: gtk::Label,
+    working_directory: gtk::FileChooserButton,
+    environment: gtk::TextView,
+    capture_output: gtk::CheckButton,
+    limits: LimitsWidgets,
+}
+
+impl LaunchDialog {
+    fn get_command_line(&self) -> String {
+        self.input
+            .get_text()
+            .map(|text| text.to_string())
+            .unwrap_or_default()
+    }
+
+    fn get_environment(&self) -> Result<Vec<(String, String)>, String> {
+        let environment = self
+            .environment
+            .get_buffer()
+            .and_then(|buffer| {
+                let (start, end) = buffer.get_bounds();
+                buffer.get_text(&start, &end, false)
+            })
+            .map(|text| text.to_string())
+            .unwrap_or_default();
+        parse_environment_lines(&environment)
+    }
+
+    /// Returns everything but the limits as an unnamed profile.
+    fn get_profile(&self) -> Result<LaunchProfile, String> {
+        Ok(LaunchProfile {
+            name: String::new(),
+            command_line: self.get_command_line(),
+            working_directory: self.working_directory.get_filename(),
+            environment: self.get_environment()?,
+            capture_output: self.capture_output.get_active(),
+        })
+    }
+
+    fn update_preview(&self) {
+        let environment = self.get_environment().unwrap_or_default();
+        match shell_words::split(&self.get_command_line(), &environment) {
+            Ok(ref args) if args.is_empty() => self.preview.set_text(""),
+            Ok(args) => self.preview.set_text(&format!("argv: {:?}", args)),
+            Err(e) => self.preview.set_text(&format!("Invalid command: {}", e)),
+        }
+    }
+
+    fn get_options(&self) -> Result<LaunchOptions, String> {
+        let mut options = self.get_profile()?.to_options();
+        options.limits = self.limits.get_limits()?;
+        Ok(options)
+    }
+
+    fn load_profile(&self, profile: &LaunchProfile) {
+        self.input.set_text(&profile.command_line);
+        match profile.working_directory {
+            Some(ref directory) => {
+                self.working_directory.set_filename(directory);
+            }
+            None => self.working_directory.unselect_all(),
+        }
+        if let Some(buffer) = self.environment.get_buffer() {
+            buffer.set_text(
+                &profile
+                    .environment
+                    .iter()
+                    .map(|(name, value)| format!("{}={}\n", name, value))
+                    .collect::<String>(),
+            );
+        }
+        self.capture_output.set_active(profile.capture_output);
+    }
+
+    /// Asks for a name and saves the content of the dialog as a profile.
+    fn save_profile(&self, history: &Rc<RefCell<LaunchHistory>>, profiles_menu: &gio::Menu) {
+        let profile = match self.get_profile() {
+            Ok(profile) => profile,
+            Err(e) => return show_result_dialog(&self.dialog, Err(e)),
+        };
+        let name_dialog = gtk::Dialog::new_with_buttons(
+            Some("Save launch profile"),
+            Some(&self.dialog),
+            gtk::DialogFlags::MODAL,
+            &[
+                ("Save", gtk::ResponseType::Ok),
+                ("Cancel", gtk::ResponseType::Close),
+            ],
+        );
+        let name = gtk::Entry::new();
+
+        if let Some(current) = self.profiles.get_active_id() {
+            name.set_text(&current);
+        }
+        name.set_placeholder_text(Some("Profile name"));
+        name.set_activates_default(true);
+        name_dialog.set_default_response(gtk::ResponseType::Ok);
+        let content_area = name_dialog.get_content_area();
+        content_area.set_border_width(10);
+        content_area.add(&name);
+
+        let profiles = self.profiles.clone();
+        name_dialog.connect_response(
+            clone!(@weak history, @strong profiles_menu => move |name_dialog, response| {
+                let name = name.get_text().map(|name| name.trim().to_owned()).unwrap_or_default();
+                if response == gtk::ResponseType::Ok && !name.is_empty() {
+                    {
+                        let mut history = history.borrow_mut();
+                        history.set_profile(LaunchProfile {
+                            name: name.clone(),
+                            ..profile.clone()
+                        });
+                        if let Err(e) = history.save() {
+                            show_result_dialog(name_dialog, Err(e));
+                        }
+                        history.fill_profiles_menu(&profiles_menu);
+                    }
+                    // The combo box "changed" handler borrows the history so it must not be
+                    // mutably borrowed anymore.
+                    fill_profiles_combo(&profiles, &history.borrow());
+                    profiles.set_active_id(Some(&name));
+                }
+                name_dialog.close();
+            }),
+        );
+        name_dialog.show_all();
+    }
+
+    fn delete_profile(&self, history: &RefCell<LaunchHistory>, profiles_menu: &gio::Menu) {
+        let name = match self.profiles.get_active_id() {
+            Some(name) => name,
+            None => return,
+        };
+        {
+            let mut history = history.borrow_mut();
+            history.remove_profile(&name);
+            if let Err(e) = history.save() {
+                show_result_dialog(&self.dialog, Err(e));
+            }
+            history.fill_profiles_menu(profiles_menu);
+        }
+        fill_profiles_combo(&self.profiles, &history.borrow());
+    }
+}
+
+/// Shows the "Launch new executable" dialog.
+pub fn show_launch_dialog(
+    window: &gtk::ApplicationWindow,
+    launched_processes: &Rc<RefCell<LaunchedProcesses>>,
+    history: &Rc<RefCell<LaunchHistory>>,
+    profiles_menu: &gio::Menu,
+) {
+    let dialog = gtk::Dialog::new_with_buttons(
+        Some("Launch new executable"),
+        Some(window),
+        gtk::DialogFlags::USE_HEADER_BAR,
+        &[
+            ("Run", gtk::ResponseType::Other(0)),
+            ("Cancel", gtk::ResponseType::Close),
+        ],
+    );
+    let (limits, limits_expander) = LimitsWidgets::new();
+    let launch_dialog = Rc::new(LaunchDialog {
+        dialog: dialog.clone(),
+        profiles: gtk::ComboBoxText::new(),
+        input: gtk::Entry::new(),
+        preview: gtk::Label::new(None),
+        working_directory: gtk::FileChooserButton::new(
+            "Select the working directory",
+            gtk::FileChooserAction::SelectFolder,
+        ),
+        environment: gtk::TextView::new(),
+        capture_output: gtk::CheckButton::new_with_label("Show the output in a log window"),
+        limits,
+    });
+    let profiles_box = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    let save_profile = gtk::Button::new_with_label("Save as profile...");
+    let delete_profile = gtk::Button::new_with_label("Delete");
+    let completion = gtk::EntryCompletion::new();
+    let completion_model = gtk::ListStore::new(&[glib::Type::String]);
+    let environment_scroll =
+        gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+    let grid = gtk::Grid::new();
+
+    // To set "run" button disabled by default.
+    dialog.set_response_sensitive(gtk::ResponseType::Other(0), false);
+    // To make "run" and "cancel" button take all spaces.
+    if let Some(run) = dialog.get_widget_for_response(gtk::ResponseType::Other(0)) {
+        if let Some(parent) = run.get_parent() {
+            match parent.downcast::<gtk::ButtonBox>() {
+                Ok(parent) => parent.set_property_layout_style(gtk::ButtonBoxStyle::Expand),
+                Err(e) => eprintln!(
+                    "<launcher::show_launch_dialog> Failed to set layout style for new task \
+                     button box: {}",
+                    e
+                ),
+            }
+        }
+    }
+    for command in &history.borrow().commands {
+        completion_model.insert_with_values(None, &[0], &[command]);
+    }
+    completion.set_model(Some(&completion_model));
+    completion.set_text_column(0);
+    fill_profiles_combo(&launch_dialog.profiles, &history.borrow());
+    launch_dialog.profiles.set_hexpand(true);
+    profiles_box.pack_start(&launch_dialog.profiles, true, true, 0);
+    profiles_box.pack_start(&save_profile, false, false, 0);
+    profiles_box.pack_start(&delete_profile, false, false, 0);
+    delete_profile.set_sensitive(false);
+    launch_dialog.input.set_hexpand(true);
+    launch_dialog.input.set_completion(Some(&completion));
+    launch_dialog.environment.set_property_monospace(true);
+    launch_dialog
+        .environment
+        .set_tooltip_text(Some("One NAME=value variable per line"));
+    environment_scroll.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+    environment_scroll.set_min_content_height(80);
+    environment_scroll.set_shadow_type(gtk::ShadowType::In);
+    environment_scroll.add(&launch_dialog.environment);
+    grid.set_row_spacing(5);
+    grid.set_column_spacing(5);
+    grid.set_border_width(5);
+    launch_dialog.preview.set_halign(gtk::Align::Start);
+    launch_dialog.preview.set_line_wrap(true);
+    launch_dialog.preview.set_selectable(true);
+    add_row(&grid, 0, "Profile:", &profiles_box);
+    add_row(&grid, 1, "Command:", &launch_dialog.input);
+    grid.attach(&launch_dialog.preview, 1, 2, 1, 1);
+    add_row(
+        &grid,
+        3,
+        "Working directory:",
+        &launch_dialog.working_directory,
+    );
+    add_row(&grid, 4, "Environment:", &environment_scroll);
+    grid.attach(&launch_dialog.capture_output, 1, 5, 1, 1);
+    grid.attach(&limits_expander, 0, 6, 2, 1);
+
+    launch_dialog.profiles.connect_changed(
+        clone!(@weak launch_dialog, @weak history, @weak delete_profile => move |profiles| {
+            let name = profiles.get_active_id();
+            delete_profile.set_sensitive(name.is_some());
+            if let Some(profile) = name.and_then(|name| history.borrow().get_profile(&name).cloned()) {
+                launch_dialog.load_profile(&profile);
+            }
+        }),
+    );
+    save_profile.connect_clicked(
+        clone!(@weak launch_dialog, @weak history, @strong profiles_menu => move |_| {
+            launch_dialog.save_profile(&history, &profiles_menu);
+        }),
+    );
+    delete_profile.connect_clicked(
+        clone!(@weak launch_dialog, @weak history, @strong profiles_menu => move |_| {
+            launch_dialog.delete_profile(&history, &profiles_menu);
+        }),
+    );
+    launch_dialog
+        .input
+        .connect_changed(clone!(@weak dialog, @weak launch_dialog => move |input| {
+            launch_dialog.update_preview();
+            match input.get_text() {
+                Some(ref x) if !x.is_empty() => {
+                    dialog.set_response_sensitive(gtk::ResponseType::Other(0), true);
+                }
+                _ => dialog.set_response_sensitive(gtk::ResponseType::Other(0), false),
+            }
+        }));
+    if let Some(buffer) = launch_dialog.environment.get_buffer() {
+        // The variables can be used in the command line.
+        buffer.connect_changed(clone!(@weak launch_dialog => move |_| {
+            launch_dialog.update_preview();
+        }));
+    }
+    launch_dialog
+        .input
+        .connect_activate(clone!(@weak dialog => move |_| {
+            dialog.response(gtk::ResponseType::Other(0));
+        }));
+    dialog.connect_response(
+        clone!(@weak window, @weak launched_processes, @weak history, @strong launch_dialog => move |dialog, response| {
+            match response {
+                gtk::ResponseType::Close => {
+                    dialog.close();
+                }
+                gtk::ResponseType::Other(0) => {
+                    let options = match launch_dialog.get_options() {
+                        Ok(ref options) if options.command_line.is_empty() => return,
+                        Ok(options) => options,
+                        // The dialog is kept open so the user can fix the invalid field.
+                        Err(e) => return show_result_dialog(dialog, Err(e)),
+                    };
+                    let result =
+                        start_detached_process(&options, &mut launched_processes.borrow_mut());
+                    if result.is_ok() {
+                        add_to_history(&history, &options.command_line);
+                    }
+                    dialog.close();
+                    show_result_dialog(&window, result);
+                }
+                _ => {}
+            }
+        }),
+    );
+
+    dialog.get_content_area().add(&grid);
+    // To silence the annying warning:
+    // "(.:2257): Gtk-WARNING **: Allocating size to GtkWindow 0x7f8a31038290 without
+    // calling gtk_widget_get_preferred_width/height(). How does the code know the size to
+    // allocate?"
+    dialog.get_preferred_width();
+    dialog.set_size_request(500, -1);
+    dialog.show_all();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(name: &str, value: &str) -> (String, String) {
+        (name.to_owned(), value.to_owned())
+    }
+
+    #[test]
+    fn environment_lines() {
+        assert_eq!(
+            parse_environment_lines("LANG=C\n\nRUST_LOG=debug\r\n   \n"),
+            Ok(vec![variable("LANG", "C"), variable("RUST_LOG", "debug")])
+        );
+        // Only the first '=' separates the name from the value.
+        assert_eq!(
+            parse_environment_lines("OPTIONS=a=1,b=2"),
+            Ok(vec![variable("OPTIONS", "a=1,b=2")])
+        );
+        // The spaces around the name are removed but the value is kept as is.
+        assert_eq!(
+            parse_environment_lines("  PREFIX = value "),
+            Ok(vec![variable("PREFIX", " value ")])
+        );
+        assert_eq!(
+            parse_environment_lines("EMPTY="),
+            Ok(vec![variable("EMPTY", "")])
+        );
+        assert_eq!(parse_environment_lines(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn invalid_environment_lines() {
+        assert_eq!(
+            parse_environment_lines("LANG=C\nINVALID"),
+            Err("Invalid environment variable \"INVALID\": expected NAME=value".to_owned())
+        );
+        assert!(parse_environment_lines("=value").is_err());
+        assert!(parse_environment_lines(" =value").is_err());
+    }
+
+    #[test]
+    fn cpu_list() {
+        assert_eq!(
+            parse_cpu_list("0", 4),
+            Some(vec![true, false, false, false])
+        );
+        assert_eq!(
+            parse_cpu_list("1-2", 4),
+            Some(vec![false, true, true, false])
+        );
+        assert_eq!(
+            parse_cpu_list("0,2-3", 4),
+            Some(vec![true, false, true, true])
+        );
+        assert_eq!(
+            parse_cpu_list(" 3 , 1 - 1", 4),
+            Some(vec![false, true, false, true])
+        );
+        // Overlapping ranges are fine.
+        assert_eq!(parse_cpu_list("0-2,1-3", 4), Some(vec![true; 4]));
+    }
+
+    #[test]
+    fn invalid_cpu_list() {
+        // Reversed ranges.
+        assert_eq!(parse_cpu_list("3-1", 4), None);
+        // Out of range CPUs.
+        assert_eq!(parse_cpu_list("4", 4), None);
+        assert_eq!(parse_cpu_list("2-4", 4), None);
+        // Empty parts.
+        assert_eq!(parse_cpu_list("", 4), None);
+        assert_eq!(parse_cpu_list("0,,2", 4), None);
+        assert_eq!(parse_cpu_list("0,", 4), None);
+        assert_eq!(parse_cpu_list("-2", 4), None);
+        assert_eq!(parse_cpu_list("1-", 4), None);
+        assert_eq!(parse_cpu_list("a-b", 4), None);
+    }
+
+    #[test]
+    fn size() {
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("512M"), Some(512 * 1024 * 1024));
+        assert_eq!(parse_size("512m"), Some(512 * 1024 * 1024));
+        assert_eq!(parse_size("512MB"), Some(512 * 1024 * 1024));
+        assert_eq!(parse_size("512MiB"), Some(512 * 1024 * 1024));
+        assert_eq!(parse_size("64k"), Some(64 * 1024));
+        assert_eq!(parse_size("1.5G"), Some(3 * 512 * 1024 * 1024));
+        assert_eq!(parse_size("2T"), Some(2 << 40));
+        assert_eq!(parse_size(" 1 G "), Some(1 << 30));
+        assert_eq!(parse_size("100b"), Some(100));
+    }
+
+    #[test]
+    fn invalid_size() {
+        assert_eq!(parse_size(""), None);
+        assert_eq!(parse_size("M"), None);
+        assert_eq!(parse_size("-1G"), None);
+        assert_eq!(parse_size("12X"), None);
+        assert_eq!(parse_size("1e30T"), None);
+    }
+}
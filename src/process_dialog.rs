@@ -1,39 +1,78 @@
+use glib::ToVariant;
 use gtk::prelude::{
-    CellLayoutExt, CellRendererTextExt, GtkListStoreExtManual, GtkWindowExt, TreeViewColumnExt,
-    TreeViewExt, WidgetExt,
+    ActionableExt, CellLayoutExt, EntryExt, GridExt, GtkListStoreExt, GtkListStoreExtManual,
+    GtkWindowExt, NotebookExtManual, PanedExt, SearchEntryExt, SpinButtonExt, ToggleButtonExt,
+    TreeModelExt, TreeModelFilterExt, TreeViewColumnExt, TreeViewExt, WidgetExt,
 };
 use gtk::{
     self, AdjustmentExt, BoxExt, ButtonExt, ContainerExt, Inhibit, LabelExt, ScrolledWindowExt,
 };
+use libc::c_int;
 use pango;
-use sysinfo::{self, Pid, ProcessExt};
+use sysinfo::{self, AsU32, Pid, ProcessExt};
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::fmt;
+use std::hash::Hash;
 use std::iter;
+use std::process::ExitStatus;
 use std::rc::Rc;
+use std::time::Instant;
 
+use display_procs::{format_rate, get_disk_rates};
+use environment::create_environment_tab;
 use graph::{Connecter, Graph};
+use launcher::describe_exit_status;
 use notebook::NoteBook;
-use utils::{connect_graph, format_number, get_main_window, RotateVec};
+use procfs::{
+    format_open_flags, get_cgroup_cpu_limit, get_cgroup_memory_limit, get_cgroups, get_clock_ticks,
+    get_connections, get_file_descriptors, get_limits, get_memory_mappings, get_namespaces,
+    get_threads, Connection, FileDescriptor, MemoryMapping, ResourceLimit, ThreadInfo,
+};
+use scheduling::{
+    describe_affinity_error, describe_error, get_affinity, get_cpu_count, get_priority,
+    set_affinity, set_priority, MAX_NICE, MIN_NICE,
+};
+use utils::{
+    connect_graph, format_date, format_number, get_main_window, get_now, save_to_file,
+    ProcessIdentity, RotateVec, StatusBar,
+};
 
 #[allow(dead_code)]
 pub struct ProcDialog {
     working_directory: gtk::Label,
     memory_usage: gtk::Label,
-    disk_usage: gtk::Label,
+    disk_read: gtk::Label,
+    disk_write: gtk::Label,
+    disk_read_total: gtk::Label,
+    disk_write_total: gtk::Label,
     cpu_usage: gtk::Label,
     run_time: gtk::Label,
+    state: gtk::Label,
     pub popup: gtk::Window,
     pub pid: Pid,
+    /// Used to detect when the process is dead and its PID has been reused by another one.
+    pub identity: ProcessIdentity,
     notebook: NoteBook,
     ram_usage_history: Rc<RefCell<Graph>>,
     cpu_usage_history: Rc<RefCell<Graph>>,
     disk_usage_history: Rc<RefCell<Graph>>,
     memory_peak: RefCell<u64>,
     memory_peak_label: gtk::Label,
+    stats: RefCell<UsageStats>,
+    name: String,
     disk_peak: RefCell<u64>,
     disk_peak_label: gtk::Label,
+    scheduling: SchedulingControls,
+    process_controls: gtk::Box,
+    /// Displays the result of the pause and resume buttons.
+    pub status_bar: StatusBar,
+    threads: Option<ThreadsTab>,
+    files: Option<FilesTab>,
+    memory_map: Option<Rc<MemoryMapTab>>,
+    connections: Option<ConnectionsTab>,
+    sandbox: Option<SandboxTab>,
     pub is_dead: bool,
     pub to_be_removed: Rc<RefCell<bool>>,
 }
@@ -45,7 +84,8 @@ impl fmt::Debug for ProcDialog {
 }
 
 impl ProcDialog {
-    pub fn update(&self, process: &sysinfo::Process, start_time: u64) {
+    /// `refresh_interval` is the number of seconds since the previous refresh of the process.
+    pub fn update(&self, process: &sysinfo::Process, start_time: u64, refresh_interval: f64) {
         if self.is_dead {
             return;
         }
@@ -58,18 +98,30 @@ impl ProcDialog {
             *self.memory_peak.borrow_mut() = memory;
             self.memory_peak_label.set_text(&memory_s);
         }
-        let disk_usage = process.disk_usage();
-        let disk_usage = disk_usage.written_bytes + disk_usage.read_bytes;
-        let disk_usage_s = format_number(disk_usage);
-        self.disk_usage.set_text(&disk_usage_s);
-        if disk_usage > *self.disk_peak.borrow() {
-            *self.disk_peak.borrow_mut() = disk_usage;
-            self.disk_peak_label.set_text(&disk_usage_s);
+        let (disk_read, disk_write) = get_disk_rates(process, refresh_interval);
+        self.disk_read.set_text(&format_rate(disk_read));
+        self.disk_write.set_text(&format_rate(disk_write));
+        if disk_read + disk_write > *self.disk_peak.borrow() {
+            *self.disk_peak.borrow_mut() = disk_read + disk_write;
+            self.disk_peak_label
+                .set_text(&format_rate(disk_read + disk_write));
         }
+        let disk_usage = process.disk_usage();
+        self.disk_read_total
+            .set_text(&format_number(disk_usage.total_read_bytes));
+        self.disk_write_total
+            .set_text(&format_number(disk_usage.total_written_bytes));
         self.cpu_usage
             .set_text(&format!("{:.1}%", process.cpu_usage()));
+        self.stats.borrow_mut().add_sample(
+            process.cpu_usage(),
+            memory,
+            disk_usage.total_read_bytes,
+            disk_usage.total_written_bytes,
+        );
         let running_since = compute_running_since(process, start_time);
         self.run_time.set_text(&format_time(running_since));
+        self.state.set_text(process.status().to_string());
 
         let mut t = self.ram_usage_history.borrow_mut();
         t.data[0].move_start();
@@ -81,27 +133,186 @@ impl ProcDialog {
         t.invalidate();
         let mut t = self.disk_usage_history.borrow_mut();
         t.data[0].move_start();
-        *t.data[0].get_mut(0).expect("cannot get data 0") = disk_usage as f64;
+        *t.data[0].get_mut(0).expect("cannot get data 0") = disk_read as f64;
+        t.data[1].move_start();
+        *t.data[1].get_mut(0).expect("cannot get data 1") = disk_write as f64;
         t.invalidate();
+
+        self.scheduling.refresh(false);
+        if let Some(ref threads) = self.threads {
+            threads.update();
+        }
+        if let Some(ref files) = self.files {
+            files.update();
+        }
+        if let Some(ref connections) = self.connections {
+            connections.update();
+        }
+        if let Some(ref sandbox) = self.sandbox {
+            sandbox.update();
+        }
     }
 
     pub fn need_remove(&self) -> bool {
         *self.to_be_removed.borrow()
     }
 
-    pub fn set_dead(&mut self) {
+    /// `exit_status` is only available if the process has been started by process-viewer.
+    pub fn set_dead(&mut self, exit_status: Option<ExitStatus>) {
         if self.is_dead {
             return;
         }
         self.is_dead = true;
         self.memory_usage.set_text("0");
-        self.disk_usage.set_text("0");
+        self.disk_read.set_text(&format_rate(0));
+        self.disk_write.set_text(&format_rate(0));
         self.cpu_usage.set_text("0%");
+        self.scheduling.container.set_sensitive(false);
+        self.process_controls.set_sensitive(false);
+        self.state.set_text("Dead");
         let s = format!(
             "Ran for {}",
             self.run_time.get_text().unwrap_or_else(|| "0s".into())
         );
         self.run_time.set_text(&s);
+        self.popup
+            .set_title(&format!("Information about {} (exited)", self.name));
+        self.show_summary(exit_status);
+    }
+
+    /// Adds a tab summing up the life of the process. The graphs aren't updated anymore so they
+    /// keep the history until the exit.
+    fn show_summary(&mut self, exit_status: Option<ExitStatus>) {
+        let now = get_now();
+        let stats = self.stats.borrow();
+        // The peaks and the averages only cover the time the dialog was open.
+        let since = format!("since {}", format_date(stats.since));
+        let summary = [
+            ("name".to_owned(), self.name.clone()),
+            ("pid".to_owned(), self.pid.to_string()),
+            (
+                "exit detected at".to_owned(),
+                format!("{} (last refresh)", format_date(now)),
+            ),
+            (
+                "exit status".to_owned(),
+                exit_status
+                    .as_ref()
+                    .map(describe_exit_status)
+                    .unwrap_or_else(|| "unknown (not started by process-viewer)".to_owned()),
+            ),
+            (
+                "total run time".to_owned(),
+                format_time(now.saturating_sub(self.identity.start_time)),
+            ),
+            (
+                format!("cpu usage peak ({})", since),
+                format!("{:.1}%", stats.cpu_peak),
+            ),
+            (
+                format!("average cpu usage ({})", since),
+                format!("{:.1}%", stats.get_average_cpu()),
+            ),
+            (
+                format!("memory usage peak ({})", since),
+                format_number(*self.memory_peak.borrow()),
+            ),
+            (
+                format!("average memory usage ({})", since),
+                format_number(stats.get_average_memory()),
+            ),
+            (
+                "total disk read".to_owned(),
+                format_number(stats.total_read),
+            ),
+            (
+                "total disk written".to_owned(),
+                format_number(stats.total_written),
+            ),
+        ];
+
+        let layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        let status_bar = StatusBar::new();
+        let save_button = gtk::Button::new_with_label("Save summary...");
+        for (title, value) in &summary {
+            create_and_add_new_label(&layout, title, value);
+        }
+        let content = summary
+            .iter()
+            .map(|(title, value)| format!("{}: {}\n", title, value))
+            .collect::<String>();
+        let file_name = format!("{}-{}-summary.txt", self.name, self.pid);
+        save_button.connect_clicked(
+            clone!(@weak self.popup as popup, @strong status_bar => move |_| {
+                match save_to_file(Some(&popup), "Save the summary", &file_name, &content) {
+                    Some(Ok(path)) => status_bar.show_message(
+                        gtk::MessageType::Info,
+                        &format!("Saved to {}", path.display()),
+                    ),
+                    Some(Err(e)) => status_bar.show_message(gtk::MessageType::Error, &e),
+                    None => {}
+                }
+            }),
+        );
+        layout.pack_start(&save_button, false, true, 0);
+        layout.pack_start(&status_bar.info_bar, false, true, 0);
+        layout.show_all();
+        status_bar.info_bar.hide();
+        if let Some(index) = self.notebook.create_tab("Summary", &layout) {
+            self.notebook.notebook.set_current_page(Some(index));
+        }
+    }
+}
+
+/// The statistics gathered since the dialog was opened, displayed in the summary once the process
+/// is dead.
+struct UsageStats {
+    /// When the dialog was opened.
+    since: u64,
+    samples: u64,
+    cpu_peak: f32,
+    cpu_sum: f64,
+    memory_sum: f64,
+    total_read: u64,
+    total_written: u64,
+}
+
+impl UsageStats {
+    fn new() -> UsageStats {
+        UsageStats {
+            since: get_now(),
+            samples: 0,
+            cpu_peak: 0.,
+            cpu_sum: 0.,
+            memory_sum: 0.,
+            total_read: 0,
+            total_written: 0,
+        }
+    }
+
+    fn add_sample(&mut self, cpu: f32, memory: u64, total_read: u64, total_written: u64) {
+        self.samples += 1;
+        if cpu > self.cpu_peak {
+            self.cpu_peak = cpu;
+        }
+        self.cpu_sum += f64::from(cpu);
+        self.memory_sum += memory as f64;
+        self.total_read = total_read;
+        self.total_written = total_written;
+    }
+
+    fn get_average_cpu(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.;
+        }
+        self.cpu_sum / self.samples as f64
+    }
+
+    fn get_average_memory(&self) -> u64 {
+        if self.samples == 0 {
+            return 0;
+        }
+        (self.memory_sum / self.samples as f64) as u64
     }
 }
 
@@ -160,27 +371,856 @@ fn create_and_add_new_label(scroll: &gtk::Box, title: &str, text: &str) -> gtk::
     text
 }
 
-fn compute_running_since(process: &sysinfo::Process, running_since: u64) -> u64 {
-    if running_since > process.start_time() {
-        running_since - process.start_time()
-    } else {
-        process.start_time() - running_since
+/// The widgets used to change the nice value and the CPU affinity of the process.
+struct SchedulingControls {
+    pid: Pid,
+    container: gtk::Grid,
+    nice: gtk::SpinButton,
+    nice_button: gtk::Button,
+    cpus: Vec<gtk::CheckButton>,
+    affinity_button: gtk::Button,
+    // The values read during the last refresh. The widgets are only updated when they change so
+    // we don't overwrite what the user is currently editing.
+    current_nice: Rc<Cell<Option<c_int>>>,
+    current_affinity: Rc<RefCell<Option<Vec<bool>>>>,
+    status_bar: StatusBar,
+}
+
+impl SchedulingControls {
+    fn new(pid: Pid) -> SchedulingControls {
+        let container = gtk::Grid::new();
+        let nice = gtk::SpinButton::new_with_range(f64::from(MIN_NICE), f64::from(MAX_NICE), 1.);
+        let cpus_grid = gtk::Grid::new();
+        let cpus = (0..get_cpu_count())
+            .map(|cpu| gtk::CheckButton::new_with_label(&cpu.to_string()))
+            .collect::<Vec<_>>();
+
+        for (pos, cpu) in cpus.iter().enumerate() {
+            cpus_grid.attach(cpu, (pos % 8) as i32, (pos / 8) as i32, 1, 1);
+        }
+
+        let nice_label = gtk::Label::new(None);
+        nice_label.set_markup("<b>nice value:</b> ");
+        nice_label.set_halign(gtk::Align::Start);
+        let affinity_label = gtk::Label::new(None);
+        affinity_label.set_markup("<b>CPU affinity:</b> ");
+        affinity_label.set_halign(gtk::Align::Start);
+        affinity_label.set_valign(gtk::Align::Start);
+
+        let controls = SchedulingControls {
+            pid,
+            container,
+            nice,
+            nice_button: gtk::Button::new_with_label("Apply"),
+            cpus,
+            affinity_button: gtk::Button::new_with_label("Apply"),
+            current_nice: Rc::new(Cell::new(None)),
+            current_affinity: Rc::new(RefCell::new(None)),
+            status_bar: StatusBar::new(),
+        };
+        controls.container.set_column_spacing(5);
+        controls.container.set_row_spacing(5);
+        controls.container.set_margin_start(5);
+        controls.container.set_margin_end(5);
+        controls.container.attach(&nice_label, 0, 0, 1, 1);
+        controls.container.attach(&controls.nice, 1, 0, 1, 1);
+        controls.container.attach(&controls.nice_button, 2, 0, 1, 1);
+        controls.container.attach(&affinity_label, 0, 1, 1, 1);
+        controls.container.attach(&cpus_grid, 1, 1, 1, 1);
+        controls
+            .container
+            .attach(&controls.affinity_button, 2, 1, 1, 1);
+        controls
+            .container
+            .attach(&controls.status_bar.info_bar, 0, 2, 3, 1);
+
+        let pid = controls.pid;
+        let nice = &controls.nice;
+        let current_nice = &controls.current_nice;
+        let status_bar = &controls.status_bar;
+        controls.nice_button.connect_clicked(
+            clone!(@weak nice, @strong current_nice, @strong status_bar => move |button| {
+                let value = nice.get_value_as_int();
+                match set_priority(pid, value) {
+                    Ok(()) => status_bar
+                        .show_message(gtk::MessageType::Info, &format!("Priority set to {}", value)),
+                    Err(e) => status_bar.show_message(
+                        gtk::MessageType::Error,
+                        &format!("Failed to change priority: {}", describe_error(&e)),
+                    ),
+                }
+                refresh_nice(pid, &nice, button, &current_nice, true);
+            }),
+        );
+        // The check buttons don't hold any reference to the "Apply" button so keeping them alive
+        // from its handler doesn't create a cycle.
+        let cpus = &controls.cpus;
+        let current_affinity = &controls.current_affinity;
+        controls.affinity_button.connect_clicked(
+            clone!(@strong cpus, @strong current_affinity, @strong status_bar => move |button| {
+                let allowed = cpus.iter().map(|cpu| cpu.get_active()).collect::<Vec<_>>();
+                match set_affinity(pid, &allowed) {
+                    Ok(()) => status_bar.show_message(gtk::MessageType::Info, "CPU affinity updated"),
+                    Err(e) => status_bar.show_message(
+                        gtk::MessageType::Error,
+                        &format!(
+                            "Failed to change CPU affinity: {}",
+                            describe_affinity_error(&e)
+                        ),
+                    ),
+                }
+                refresh_affinity(pid, &cpus, button, &current_affinity, true);
+            }),
+        );
+        controls.refresh(true);
+        controls
+    }
+
+    /// Reads the current values from the system. If `force` is `false`, the widgets are only
+    /// updated if the values changed since the last refresh.
+    fn refresh(&self, force: bool) {
+        refresh_nice(
+            self.pid,
+            &self.nice,
+            &self.nice_button,
+            &self.current_nice,
+            force,
+        );
+        refresh_affinity(
+            self.pid,
+            &self.cpus,
+            &self.affinity_button,
+            &self.current_affinity,
+            force,
+        );
+    }
+}
+
+fn refresh_nice(
+    pid: Pid,
+    nice: &gtk::SpinButton,
+    button: &gtk::Button,
+    current_nice: &Cell<Option<c_int>>,
+    force: bool,
+) {
+    let value = get_priority(pid).ok();
+    if force || value != current_nice.get() {
+        if let Some(value) = value {
+            nice.set_value(f64::from(value));
+        }
+        current_nice.set(value);
+    }
+    nice.set_sensitive(value.is_some());
+    button.set_sensitive(value.is_some());
+}
+
+fn refresh_affinity(
+    pid: Pid,
+    cpus: &[gtk::CheckButton],
+    button: &gtk::Button,
+    current_affinity: &RefCell<Option<Vec<bool>>>,
+    force: bool,
+) {
+    let affinity = get_affinity(pid).ok();
+    if force || affinity != *current_affinity.borrow() {
+        if let Some(ref affinity) = affinity {
+            for (cpu, allowed) in cpus.iter().zip(affinity.iter()) {
+                cpu.set_active(*allowed);
+            }
+        }
+    }
+    for cpu in cpus {
+        cpu.set_sensitive(affinity.is_some());
     }
+    button.set_sensitive(affinity.is_some());
+    *current_affinity.borrow_mut() = affinity;
 }
 
-fn append_text_column(tree: &gtk::TreeView, pos: i32) -> gtk::CellRendererText {
+/// Adds a column displaying the text of the model column `pos` and sorted with the model column
+/// `sort_pos`.
+fn append_sortable_column(tree: &gtk::TreeView, title: &str, pos: i32, sort_pos: i32) {
     let column = gtk::TreeViewColumn::new();
     let cell = gtk::CellRendererText::new();
 
+    column.set_title(title);
+    column.set_resizable(true);
     column.pack_start(&cell, true);
     column.add_attribute(&cell, "text", pos);
-    if pos == 1 {
-        cell.set_property_wrap_width(247);
-        cell.set_property_wrap_mode(pango::WrapMode::Char);
-        column.set_expand(true);
-    }
+    column.set_sort_column_id(sort_pos);
     tree.append_column(&column);
-    cell
+}
+
+/// Updates the rows which are already in the list (the first column being the key) and removes
+/// the ones which aren't in `rows` anymore. The remaining rows are then added at the end.
+fn update_list_store<K, T, F>(list_store: &gtk::ListStore, mut rows: HashMap<K, T>, set_row: F)
+where
+    K: for<'a> glib::value::FromValueOptional<'a> + Hash + Ord + Clone,
+    F: Fn(&gtk::TreeIter, &T),
+{
+    if let Some(iter) = list_store.get_iter_first() {
+        loop {
+            let row = list_store
+                .get_value(&iter, 0)
+                .get::<K>()
+                .unwrap_or(None)
+                .and_then(|key| rows.remove(&key));
+            let is_valid = match row {
+                Some(row) => {
+                    set_row(&iter, &row);
+                    list_store.iter_next(&iter)
+                }
+                None => list_store.remove(&iter),
+            };
+            if !is_valid {
+                break;
+            }
+        }
+    }
+    let mut keys = rows.keys().cloned().collect::<Vec<_>>();
+    keys.sort();
+    for key in keys {
+        set_row(&list_store.append(), &rows[&key]);
+    }
+}
+
+/// The open file descriptors of the process.
+struct FilesTab {
+    pid: Pid,
+    list_store: gtk::ListStore,
+    count: gtk::Label,
+}
+
+impl FilesTab {
+    /// Returns `None` if the file descriptors of the process cannot be retrieved.
+    fn new(pid: Pid, notebook: &mut NoteBook) -> Option<FilesTab> {
+        let fds = get_file_descriptors(pid).ok()?;
+        let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 5);
+        let filter_entry = gtk::SearchEntry::new();
+        let tree = gtk::TreeView::new();
+        let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+        let list_store = gtk::ListStore::new(&[
+            glib::Type::U32,    // fd
+            glib::Type::String, // type
+            glib::Type::String, // target
+            glib::Type::String, // flags
+            glib::Type::String, // position
+            glib::Type::U64,    // position (used to sort)
+        ]);
+        let filter_model = gtk::TreeModelFilter::new(&list_store, None);
+
+        filter_model.set_visible_func(
+            clone!(@weak filter_entry => @default-return true, move |model, iter| {
+                let text = match filter_entry.get_text() {
+                    Some(text) if !text.is_empty() => text.to_lowercase(),
+                    _ => return true,
+                };
+                (0..5).any(|pos| {
+                    let value = model.get_value(iter, pos);
+                    let value = match value.get::<String>() {
+                        Ok(Some(s)) => s,
+                        _ => value.get::<u32>().unwrap_or(None).map(|v| v.to_string()).unwrap_or_default(),
+                    };
+                    value.to_lowercase().contains(&text)
+                })
+            }),
+        );
+        filter_entry.connect_search_changed(clone!(@weak filter_model => move |_| {
+            filter_model.refilter();
+        }));
+        tree.set_model(Some(&gtk::TreeModelSort::new(&filter_model)));
+        for (title, pos, sort_pos) in &[
+            ("fd", 0, 0),
+            ("type", 1, 1),
+            ("target", 2, 2),
+            ("flags", 3, 3),
+            ("position", 4, 5),
+        ] {
+            append_sortable_column(&tree, title, *pos, *sort_pos);
+        }
+
+        let count = gtk::Label::new(None);
+        count.set_halign(gtk::Align::Start);
+        filter_entry.set_placeholder_text(Some("Filter"));
+        scroll.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+        scroll.add(&tree);
+        vertical_layout.set_margin_top(5);
+        vertical_layout.pack_start(&filter_entry, false, true, 0);
+        vertical_layout.pack_start(&count, false, true, 0);
+        vertical_layout.pack_start(&scroll, true, true, 0);
+        notebook.create_tab("Open files", &vertical_layout);
+
+        let tab = FilesTab {
+            pid,
+            list_store,
+            count,
+        };
+        tab.fill(fds);
+        Some(tab)
+    }
+
+    fn update(&self) {
+        if let Ok(fds) = get_file_descriptors(self.pid) {
+            self.fill(fds);
+        }
+    }
+
+    fn fill(&self, fds: Vec<FileDescriptor>) {
+        // The limit can be changed at runtime (with `prlimit` for example).
+        let limit = get_limits(self.pid)
+            .ok()
+            .and_then(|limits| limits.into_iter().find(|l| l.name == "Max open files"))
+            .map(|limit| match limit.soft {
+                Some(soft) => soft.to_string(),
+                None => "unlimited".to_owned(),
+            });
+        self.count.set_markup(&format!(
+            "<b>{}</b> open file descriptors (soft limit: {})",
+            fds.len(),
+            limit.unwrap_or_else(|| "unknown".to_owned())
+        ));
+
+        let fds = fds.into_iter().map(|fd| (fd.fd, fd)).collect();
+        update_list_store(&self.list_store, fds, |iter, fd| {
+            self.list_store.set(
+                iter,
+                &[0, 1, 2, 3, 4, 5],
+                &[
+                    &fd.fd,
+                    &fd.kind,
+                    &fd.target,
+                    &fd.flags.map(format_open_flags).unwrap_or_default(),
+                    &fd.position.map(|p| p.to_string()).unwrap_or_default(),
+                    &fd.position.unwrap_or(0),
+                ],
+            );
+        });
+    }
+}
+
+/// The sizes displayed for each memory mapping and in the per-file summary.
+fn get_mapping_sizes(mapping: &MemoryMapping) -> [u64; 6] {
+    [
+        mapping.end - mapping.start,
+        mapping.rss,
+        mapping.pss,
+        mapping.swap,
+        mapping.shared_dirty,
+        mapping.private_dirty,
+    ]
+}
+
+/// The memory mappings of the process, and the same information summed by mapped file.
+struct MemoryMapTab {
+    pid: Pid,
+    mappings: gtk::ListStore,
+    summary: gtk::ListStore,
+}
+
+impl MemoryMapTab {
+    /// Returns `None` if the memory mappings of the process cannot be retrieved.
+    fn new(pid: Pid, notebook: &mut NoteBook) -> Option<Rc<MemoryMapTab>> {
+        let mappings = get_memory_mappings(pid).ok()?;
+        let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 5);
+        let paned = gtk::Paned::new(gtk::Orientation::Vertical);
+        let refresh_button = gtk::Button::new_with_label("Refresh");
+        // The sizes are displayed in the columns 3 to 8 and are sorted with the columns 9 to 14.
+        let mut types = vec![glib::Type::String; 9];
+        types.extend(&[glib::Type::U64; 6]);
+
+        // The first columns are: address range, permissions, file.
+        let mappings_store = gtk::ListStore::new(&types);
+        // The first columns are: file, number of mappings (and a padding one).
+        let summary_store = gtk::ListStore::new(&types);
+        let mappings_tree = gtk::TreeView::new_with_model(&mappings_store);
+        let summary_tree = gtk::TreeView::new_with_model(&summary_store);
+
+        append_sortable_column(&mappings_tree, "address range", 0, 0);
+        append_sortable_column(&mappings_tree, "permissions", 1, 1);
+        append_sortable_column(&mappings_tree, "file", 2, 2);
+        append_sortable_column(&summary_tree, "file", 0, 0);
+        append_sortable_column(&summary_tree, "mappings", 1, 1);
+        for tree in &[&mappings_tree, &summary_tree] {
+            for (pos, title) in [
+                "size",
+                "rss",
+                "pss",
+                "swap",
+                "shared dirty",
+                "private dirty",
+            ]
+            .iter()
+            .enumerate()
+            {
+                append_sortable_column(tree, title, pos as i32 + 3, pos as i32 + 9);
+            }
+        }
+
+        for (tree, title) in &[
+            (&summary_tree, "<b>Summary by file</b>"),
+            (&mappings_tree, "<b>Mappings</b>"),
+        ] {
+            let layout = gtk::Box::new(gtk::Orientation::Vertical, 5);
+            let label = gtk::Label::new(None);
+            let scroll =
+                gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+
+            label.set_markup(title);
+            scroll.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+            scroll.add(*tree);
+            layout.pack_start(&label, false, true, 0);
+            layout.pack_start(&scroll, true, true, 0);
+            if paned.get_child1().is_none() {
+                paned.pack1(&layout, true, false);
+            } else {
+                paned.pack2(&layout, true, false);
+            }
+        }
+        vertical_layout.set_margin_top(5);
+        vertical_layout.pack_start(&paned, true, true, 0);
+        vertical_layout.pack_start(&refresh_button, false, true, 0);
+        notebook.create_tab("Memory map", &vertical_layout);
+
+        let tab = Rc::new(MemoryMapTab {
+            pid,
+            mappings: mappings_store,
+            summary: summary_store,
+        });
+        tab.fill(&mappings);
+        // Reading the memory mappings can be slow so it's only done when asked.
+        refresh_button.connect_clicked(clone!(@weak tab => move |_| {
+            if let Ok(mappings) = get_memory_mappings(tab.pid) {
+                tab.fill(&mappings);
+            }
+        }));
+        Some(tab)
+    }
+
+    fn fill(&self, mappings: &[MemoryMapping]) {
+        let mut summary: HashMap<&str, (u32, [u64; 6])> = HashMap::new();
+
+        self.mappings.clear();
+        for mapping in mappings {
+            let path = if mapping.path.is_empty() {
+                "[anonymous]"
+            } else {
+                mapping.path.as_str()
+            };
+            let sizes = get_mapping_sizes(mapping);
+            let entry = summary.entry(path).or_insert((0, [0; 6]));
+            entry.0 += 1;
+            for (total, size) in entry.1.iter_mut().zip(sizes.iter()) {
+                *total += size;
+            }
+            self.insert_row(
+                &self.mappings,
+                &[
+                    &format!("{:x}-{:x}", mapping.start, mapping.end),
+                    &mapping.permissions,
+                    &path,
+                ],
+                &sizes,
+            );
+        }
+
+        self.summary.clear();
+        for (path, (count, sizes)) in summary {
+            self.insert_row(&self.summary, &[&path, &count.to_string(), &""], &sizes);
+        }
+    }
+
+    fn insert_row(
+        &self,
+        list_store: &gtk::ListStore,
+        first: &[&dyn glib::ToValue; 3],
+        sizes: &[u64],
+    ) {
+        let formatted = sizes.iter().map(|s| format_number(*s)).collect::<Vec<_>>();
+        let mut values: Vec<&dyn glib::ToValue> = first.to_vec();
+        values.extend(formatted.iter().map(|s| s as &dyn glib::ToValue));
+        values.extend(sizes.iter().map(|s| s as &dyn glib::ToValue));
+        let columns = (0..values.len() as u32).collect::<Vec<_>>();
+        list_store.insert_with_values(None, &columns, &values);
+    }
+}
+
+/// The sockets opened by the process.
+struct ConnectionsTab {
+    pid: Pid,
+    list_store: gtk::ListStore,
+    count: gtk::Label,
+}
+
+impl ConnectionsTab {
+    /// Returns `None` if the sockets of the process cannot be retrieved.
+    fn new(pid: Pid, notebook: &mut NoteBook) -> Option<ConnectionsTab> {
+        let connections = get_connections(pid).ok()?;
+        let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 5);
+        let tree = gtk::TreeView::new();
+        let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+        let list_store = gtk::ListStore::new(&[
+            glib::Type::U64,    // inode
+            glib::Type::String, // protocol
+            glib::Type::String, // local address
+            glib::Type::String, // remote address
+            glib::Type::String, // state
+            glib::Type::String, // send queue
+            glib::Type::String, // receive queue
+            glib::Type::U64,    // send queue (used to sort)
+            glib::Type::U64,    // receive queue (used to sort)
+        ]);
+
+        tree.set_model(Some(&list_store));
+        for (title, pos, sort_pos) in &[
+            ("protocol", 1, 1),
+            ("local address", 2, 2),
+            ("remote address", 3, 3),
+            ("state", 4, 4),
+            ("send queue", 5, 7),
+            ("receive queue", 6, 8),
+            ("inode", 0, 0),
+        ] {
+            append_sortable_column(&tree, title, *pos, *sort_pos);
+        }
+
+        let count = gtk::Label::new(None);
+        count.set_halign(gtk::Align::Start);
+        scroll.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+        scroll.add(&tree);
+        vertical_layout.set_margin_top(5);
+        vertical_layout.pack_start(&count, false, true, 0);
+        vertical_layout.pack_start(&scroll, true, true, 0);
+        notebook.create_tab("Connections", &vertical_layout);
+
+        let tab = ConnectionsTab {
+            pid,
+            list_store,
+            count,
+        };
+        tab.fill(connections);
+        Some(tab)
+    }
+
+    fn update(&self) {
+        if let Ok(connections) = get_connections(self.pid) {
+            self.fill(connections);
+        }
+    }
+
+    fn fill(&self, connections: Vec<Connection>) {
+        self.count
+            .set_markup(&format!("<b>{}</b> sockets", connections.len()));
+
+        let connections = connections.into_iter().map(|c| (c.inode, c)).collect();
+        update_list_store(&self.list_store, connections, |iter, connection| {
+            let queue = |q: Option<u64>| q.map(|q| q.to_string()).unwrap_or_default();
+            self.list_store.set(
+                iter,
+                &[0, 1, 2, 3, 4, 5, 6, 7, 8],
+                &[
+                    &connection.inode,
+                    &connection.protocol,
+                    &connection.local_address,
+                    &connection.remote_address,
+                    &connection.state,
+                    &queue(connection.send_queue),
+                    &queue(connection.receive_queue),
+                    &connection.send_queue.unwrap_or(0),
+                    &connection.receive_queue.unwrap_or(0),
+                ],
+            );
+        });
+    }
+}
+
+/// The resource limits, control groups and namespaces of the process.
+struct SandboxTab {
+    pid: Pid,
+    limits: gtk::ListStore,
+    cgroups: gtk::Label,
+    memory_limit: gtk::Label,
+    cpu_limit: gtk::Label,
+}
+
+impl SandboxTab {
+    /// Returns `None` if the resource limits of the process cannot be retrieved.
+    fn new(pid: Pid, notebook: &mut NoteBook) -> Option<SandboxTab> {
+        let limits = get_limits(pid).ok()?;
+        let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+        let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        let limits_store = gtk::ListStore::new(&[
+            glib::Type::String, // name
+            glib::Type::String, // soft limit
+            glib::Type::String, // hard limit
+        ]);
+        let limits_tree = gtk::TreeView::new_with_model(&limits_store);
+
+        for (pos, title) in ["limit", "soft", "hard"].iter().enumerate() {
+            let column = gtk::TreeViewColumn::new();
+            let cell = gtk::CellRendererText::new();
+
+            column.set_title(title);
+            column.set_resizable(true);
+            column.pack_start(&cell, true);
+            column.add_attribute(&cell, "text", pos as i32);
+            limits_tree.append_column(&column);
+        }
+
+        add_section_title(&vertical_layout, "Namespaces");
+        add_namespaces(&vertical_layout, pid);
+        add_section_title(&vertical_layout, "Control groups");
+        let cgroups = create_and_add_new_label(&vertical_layout, "cgroups", "");
+        let memory_limit = create_and_add_new_label(&vertical_layout, "memory limit", "");
+        let cpu_limit = create_and_add_new_label(&vertical_layout, "CPU limit", "");
+        add_section_title(&vertical_layout, "Resource limits");
+        vertical_layout.add(&limits_tree);
+
+        scroll.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scroll.add(&vertical_layout);
+        notebook.create_tab("Sandbox", &scroll);
+
+        let tab = SandboxTab {
+            pid,
+            limits: limits_store,
+            cgroups,
+            memory_limit,
+            cpu_limit,
+        };
+        tab.fill_limits(limits);
+        tab.update_cgroups();
+        Some(tab)
+    }
+
+    fn update(&self) {
+        // The limits can be changed at runtime (with `prlimit` for example) and the process can
+        // be moved to another cgroup.
+        if let Ok(limits) = get_limits(self.pid) {
+            self.fill_limits(limits);
+        }
+        self.update_cgroups();
+    }
+
+    fn fill_limits(&self, limits: Vec<ResourceLimit>) {
+        let format_limit = |limit: Option<u64>, unit: &str| match limit {
+            None => "unlimited".to_owned(),
+            Some(limit) if unit == "bytes" => format_number(limit),
+            Some(limit) => format!("{} {}", limit, unit),
+        };
+        let limits = limits.into_iter().map(|l| (l.name.clone(), l)).collect();
+
+        update_list_store(&self.limits, limits, |iter, limit| {
+            self.limits.set(
+                iter,
+                &[0, 1, 2],
+                &[
+                    &limit.name,
+                    &format_limit(limit.soft, &limit.unit),
+                    &format_limit(limit.hard, &limit.unit),
+                ],
+            );
+        });
+    }
+
+    fn update_cgroups(&self) {
+        let cgroups = match get_cgroups(self.pid) {
+            Ok(cgroups) => cgroups,
+            Err(_) => {
+                self.cgroups.set_text("unknown");
+                return;
+            }
+        };
+        let text = cgroups
+            .iter()
+            .map(|cgroup| {
+                if cgroup.is_v2() {
+                    format!("{} (v2)", cgroup.path)
+                } else {
+                    format!("{} ({})", cgroup.path, cgroup.controllers)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.cgroups.set_text(&text);
+
+        self.memory_limit
+            .set_text(&match get_cgroup_memory_limit(&cgroups) {
+                Ok(Some(limit)) => format_number(limit),
+                Ok(None) => "unlimited".to_owned(),
+                Err(_) => "unknown".to_owned(),
+            });
+        self.cpu_limit
+            .set_text(&match get_cgroup_cpu_limit(&cgroups) {
+                Ok(Some(limit)) => format!("{:.2} CPUs", limit),
+                Ok(None) => "unlimited".to_owned(),
+                Err(_) => "unknown".to_owned(),
+            });
+    }
+}
+
+fn add_section_title(layout: &gtk::Box, title: &str) {
+    let label = gtk::Label::new(None);
+
+    label.set_markup(&format!("<big><b>{}</b></big>", title));
+    label.set_halign(gtk::Align::Start);
+    label.set_margin_top(10);
+    label.set_margin_start(5);
+    layout.add(&label);
+}
+
+/// Namespaces which are different from the ones of process-viewer are highlighted.
+fn add_namespaces(layout: &gtk::Box, pid: Pid) {
+    let namespaces = match get_namespaces(pid) {
+        Ok(namespaces) if !namespaces.is_empty() => namespaces,
+        _ => {
+            let label = gtk::Label::new(Some("Cannot read the namespaces of this process"));
+            label.set_halign(gtk::Align::Start);
+            label.set_margin_start(5);
+            layout.add(&label);
+            return;
+        }
+    };
+    let own = get_namespaces(::std::process::id() as Pid).unwrap_or_default();
+    let grid = gtk::Grid::new();
+
+    grid.set_column_spacing(10);
+    grid.set_margin_start(5);
+    grid.set_margin_top(5);
+    for (row, namespace) in namespaces.iter().enumerate() {
+        let same = own
+            .iter()
+            .find(|n| n.name == namespace.name)
+            .map(|n| n.inode == namespace.inode);
+        let name = gtk::Label::new(None);
+        let inode = gtk::Label::new(Some(&namespace.inode.to_string()));
+        let status = gtk::Label::new(None);
+
+        inode.set_selectable(true);
+        match same {
+            Some(false) => {
+                name.set_markup(&format!("<b>{}</b>", namespace.name));
+                status.set_markup(
+                    "<span foreground=\"red\"><b>different from process-viewer</b></span>",
+                );
+            }
+            _ => name.set_text(&namespace.name),
+        }
+        for (column, label) in [&name, &inode, &status].iter().enumerate() {
+            label.set_halign(gtk::Align::Start);
+            grid.attach(*label, column as i32, row as i32, 1, 1);
+        }
+    }
+    layout.add(&grid);
+}
+
+/// The list of the threads of the process.
+struct ThreadsTab {
+    pid: Pid,
+    list_store: gtk::ListStore,
+    // The CPU time of each thread during the last refresh, to compute their CPU usage.
+    previous_cpu_times: RefCell<HashMap<Pid, u64>>,
+    last_update: Cell<Instant>,
+    clock_ticks: u64,
+}
+
+impl ThreadsTab {
+    /// Returns `None` if the threads of the process cannot be retrieved.
+    fn new(pid: Pid, notebook: &mut NoteBook) -> Option<ThreadsTab> {
+        let threads = get_threads(pid).ok()?;
+        let tree = gtk::TreeView::new();
+        let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+        let list_store = gtk::ListStore::new(&[
+            glib::Type::U32,    // tid
+            glib::Type::String, // name
+            glib::Type::String, // state
+            glib::Type::String, // CPU usage
+            glib::Type::String, // last CPU
+            glib::Type::F32,    // CPU usage (used to sort)
+            glib::Type::U32,    // last CPU (used to sort)
+        ]);
+
+        tree.set_model(Some(&list_store));
+        for (title, pos, sort_pos) in &[
+            ("tid", 0, 0),
+            ("name", 1, 1),
+            ("state", 2, 2),
+            ("cpu usage", 3, 5),
+            ("last cpu", 4, 6),
+        ] {
+            append_sortable_column(&tree, title, *pos, *sort_pos);
+        }
+        scroll.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+        scroll.add(&tree);
+        notebook.create_tab("Threads", &scroll);
+
+        let tab = ThreadsTab {
+            pid,
+            list_store,
+            previous_cpu_times: RefCell::new(HashMap::new()),
+            last_update: Cell::new(Instant::now()),
+            clock_ticks: get_clock_ticks(),
+        };
+        tab.fill(threads);
+        Some(tab)
+    }
+
+    fn update(&self) {
+        if let Ok(threads) = get_threads(self.pid) {
+            self.fill(threads);
+        }
+    }
+
+    fn fill(&self, threads: Vec<ThreadInfo>) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update.get()).as_secs_f64();
+        let mut previous_cpu_times = self.previous_cpu_times.borrow_mut();
+
+        self.last_update.set(now);
+        let threads = threads
+            .into_iter()
+            .map(|thread| {
+                let cpu_usage = match previous_cpu_times.get(&thread.tid) {
+                    Some(previous) if elapsed > 0. => {
+                        thread.cpu_time.saturating_sub(*previous) as f64
+                            / self.clock_ticks as f64
+                            / elapsed
+                            * 100.
+                    }
+                    _ => 0.,
+                };
+                (thread.tid as u32, (thread, cpu_usage as f32))
+            })
+            .collect::<HashMap<_, _>>();
+        previous_cpu_times.clear();
+        for (thread, _) in threads.values() {
+            previous_cpu_times.insert(thread.tid, thread.cpu_time);
+        }
+        update_list_store(&self.list_store, threads, |iter, (thread, cpu_usage)| {
+            self.set_row(iter, thread, *cpu_usage)
+        });
+    }
+
+    fn set_row(&self, iter: &gtk::TreeIter, thread: &ThreadInfo, cpu_usage: f32) {
+        self.list_store.set(
+            iter,
+            &[0, 1, 2, 3, 4, 5, 6],
+            &[
+                &(thread.tid as u32),
+                &thread.name,
+                &thread.state,
+                &format!("{:.1}%", cpu_usage),
+                &thread.processor.map(|p| p.to_string()).unwrap_or_default(),
+                &cpu_usage,
+                &thread.processor.unwrap_or(0),
+            ],
+        );
+    }
+}
+
+fn compute_running_since(process: &sysinfo::Process, running_since: u64) -> u64 {
+    if running_since > process.start_time() {
+        running_since - process.start_time()
+    } else {
+        process.start_time() - running_since
+    }
 }
 
 pub fn create_process_dialog(
@@ -215,26 +1255,38 @@ pub fn create_process_dialog(
         create_and_add_new_label(&labels, "memory usage", &format_number(memory_peak));
     let memory_peak_label =
         create_and_add_new_label(&labels, "memory usage peak", &format_number(memory_peak));
-    let disk_peak = process.disk_usage();
-    let disk_peak = disk_peak.written_bytes + disk_peak.read_bytes;
+    // The rates are only known after the next refresh.
+    let disk_usage = process.disk_usage();
     let s;
     #[cfg(not(windows))]
     {
-        s = "disk I/O usage";
+        s = "disk";
     }
     #[cfg(windows)]
     {
-        s = "I/O usage";
+        s = "I/O";
     }
-    let disk_usage = create_and_add_new_label(&labels, s, &format_number(disk_peak));
+    let disk_read = create_and_add_new_label(&labels, &format!("{} read", s), &format_rate(0));
+    let disk_write = create_and_add_new_label(&labels, &format!("{} write", s), &format_rate(0));
     let disk_peak_label =
-        create_and_add_new_label(&labels, &format!("{} peak", s), &format_number(disk_peak));
+        create_and_add_new_label(&labels, &format!("{} usage peak", s), &format_rate(0));
+    let disk_read_total = create_and_add_new_label(
+        &labels,
+        &format!("total {} read", s),
+        &format_number(disk_usage.total_read_bytes),
+    );
+    let disk_write_total = create_and_add_new_label(
+        &labels,
+        &format!("total {} written", s),
+        &format_number(disk_usage.total_written_bytes),
+    );
     let cpu_usage = create_and_add_new_label(
         &labels,
         "cpu usage",
         &format!("{:.1}%", process.cpu_usage()),
     );
     let run_time = create_and_add_new_label(&labels, "Running since", &format_time(running_since));
+    let state = create_and_add_new_label(&labels, "state", process.status().to_string());
     create_and_add_new_label(
         &labels,
         "command",
@@ -264,41 +1316,30 @@ pub fn create_process_dialog(
         &process.root().display().to_string(),
     );
 
-    let env_tree = gtk::TreeView::new();
-    let list_store = gtk::ListStore::new(&[glib::Type::String, glib::Type::String]);
-
-    env_tree.set_headers_visible(false);
-    env_tree.set_model(Some(&list_store));
-
-    append_text_column(&env_tree, 0);
-    let cell = append_text_column(&env_tree, 1);
-
-    env_tree.connect_size_allocate(move |tree, _| {
-        if let Some(col) = tree.get_column(1) {
-            cell.set_property_wrap_width(col.get_width() - 1);
-        }
-    });
+    let scheduling = SchedulingControls::new(process.pid());
 
-    for env in process.environ() {
-        let mut parts = env.splitn(2, '=');
-        let name = match parts.next() {
-            Some(n) => n,
-            None => continue,
-        };
-        let value = parts.next().unwrap_or_else(|| "");
-        list_store.insert_with_values(None, &[0, 1], &[&name, &value]);
+    // The signals are sent by the application actions so the stopped processes are tracked in a
+    // single place.
+    let process_controls = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    process_controls.set_margin_top(5);
+    process_controls.set_margin_start(5);
+    process_controls.set_margin_end(5);
+    for (label, action) in &[
+        ("Pause", "app.pause-process"),
+        ("Resume", "app.resume-process"),
+    ] {
+        let button = gtk::Button::new_with_label(label);
+        button.set_action_name(Some(action));
+        button.set_action_target_value(Some(&(process.pid().as_u32() as i32).to_variant()));
+        process_controls.pack_start(&button, true, true, 0);
     }
+    let status_bar = StatusBar::new();
 
     let components = gtk::Box::new(gtk::Orientation::Vertical, 0);
     components.add(&labels);
-
-    if !process.environ().is_empty() {
-        let label = gtk::Label::new(None);
-        label.set_markup("<b>Environment variables</b>");
-
-        components.add(&label);
-        components.pack_start(&env_tree, false, false, 0);
-    }
+    components.add(&scheduling.container);
+    components.add(&process_controls);
+    components.add(&status_bar.info_bar);
 
     scroll.add(&components);
 
@@ -326,7 +1367,8 @@ pub fn create_process_dialog(
     ram_usage_history.set_overhead(Some(20.));
 
     let mut disk_usage_history = Graph::new(Some(0f64), false);
-    disk_usage_history.set_display_labels(false);
+    // There are two lines (read and write) so we need to display which is which.
+    disk_usage_history.set_display_labels(true);
     disk_usage_history.set_overhead(Some(20.));
 
     cpu_usage_history.push(
@@ -363,11 +1405,9 @@ pub fn create_process_dialog(
         None,
     );
 
-    disk_usage_history.push(
-        RotateVec::new(iter::repeat(0f64).take(61).collect()),
-        "",
-        None,
-    );
+    for line in &["read", "write"] {
+        disk_usage_history.push(RotateVec::new(vec![0f64; 61]), line, None);
+    }
 
     fn nb_label(v: f64) -> [String; 4] {
         if v < 100_000. {
@@ -411,11 +1451,11 @@ pub fn create_process_dialog(
 
     #[cfg(not(windows))]
     {
-        vertical_layout.add(&gtk::Label::new(Some("Disk I/O usage")));
+        vertical_layout.add(&gtk::Label::new(Some("Disk I/O usage (per second)")));
     }
     #[cfg(windows)]
     {
-        vertical_layout.add(&gtk::Label::new(Some("I/O usage")));
+        vertical_layout.add(&gtk::Label::new(Some("I/O usage (per second)")));
     }
     disk_usage_history.attach_to(&vertical_layout);
     disk_usage_history.invalidate();
@@ -431,6 +1471,18 @@ pub fn create_process_dialog(
     );
     notebook.create_tab("Resources usage", &scroll);
 
+    let threads = ThreadsTab::new(process.pid(), &mut notebook);
+    let files = FilesTab::new(process.pid(), &mut notebook);
+    let memory_map = MemoryMapTab::new(process.pid(), &mut notebook);
+    let connections = ConnectionsTab::new(process.pid(), &mut notebook);
+    create_environment_tab(
+        process.pid(),
+        process.name(),
+        process.environ(),
+        &mut notebook,
+    );
+    let sandbox = SandboxTab::new(process.pid(), &mut notebook);
+
     popup.add(&notebook.notebook);
     // To silence the annoying warning:
     // "(.:2257): Gtk-WARNING **: Allocating size to GtkWindow 0x7f8a31038290 without
@@ -466,19 +1518,34 @@ pub fn create_process_dialog(
     ProcDialog {
         working_directory,
         memory_usage,
-        disk_usage,
+        disk_read,
+        disk_write,
+        disk_read_total,
+        disk_write_total,
         cpu_usage,
         run_time,
+        state,
         popup,
         pid: process.pid(),
+        identity: ProcessIdentity::new(process),
         notebook,
         ram_usage_history,
         cpu_usage_history,
         disk_usage_history,
         memory_peak: RefCell::new(memory_peak),
         memory_peak_label,
-        disk_peak: RefCell::new(disk_peak),
+        stats: RefCell::new(UsageStats::new()),
+        name: process.name().to_owned(),
+        disk_peak: RefCell::new(0),
         disk_peak_label,
+        scheduling,
+        process_controls,
+        status_bar,
+        threads,
+        files,
+        memory_map,
+        connections,
+        sandbox,
         is_dead: false,
         to_be_removed,
     }
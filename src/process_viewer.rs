@@ -15,6 +15,7 @@ extern crate glib;
 extern crate gtk;
 extern crate libc;
 extern crate pango;
+extern crate regex;
 extern crate sysinfo;
 extern crate toml;
 
@@ -26,195 +27,266 @@ use sysinfo::*;
 use gdk_pixbuf::Pixbuf;
 use gio::prelude::{ActionExt, ActionMapExt, ApplicationExt, ApplicationExtManual};
 use gio::MemoryInputStream;
-use glib::{Bytes, Cast, IsA, ToVariant};
+use glib::{Bytes, Cast, ToVariant};
 use gtk::prelude::{
-    AboutDialogExt, BoxExt, ButtonBoxExt, ButtonExt, ContainerExt, DialogExt, EntryExt,
-    GtkApplicationExt, GtkListStoreExt, GtkListStoreExtManual, GtkWindowExt, GtkWindowExtManual,
-    NotebookExtManual, SearchBarExt, TreeModelExt, TreeSortableExtManual, TreeViewExt, WidgetExt,
+    AboutDialogExt, BoxExt, ButtonExt, ComboBoxExt, ContainerExt, DialogExt, EntryExt,
+    GtkApplicationExt, GtkWindowExt, GtkWindowExtManual, NotebookExtManual, SearchBarExt,
+    ToggleButtonExt, TreeModelExt, TreeSortableExtManual, TreeStoreExt, TreeViewExt, WidgetExt,
     WidgetExtManual,
 };
-use gtk::{AboutDialog, Dialog, EditableSignals, Entry, Inhibit, MessageDialog};
+use gtk::{AboutDialog, Inhibit};
 
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::env::args;
-#[cfg(unix)]
-use std::os::unix::process::CommandExt;
-use std::process::{Command, Stdio};
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant};
 
 mod color;
+mod columns;
 mod display_disk;
 #[macro_use]
 mod display_sysinfo;
 mod display_network;
 mod display_procs;
+mod environment;
 mod graph;
+mod launch_history;
+mod launcher;
 mod network_dialog;
 mod notebook;
 mod process_dialog;
+mod procfs;
+mod query;
+mod scheduling;
 mod settings;
+mod shell_words;
+mod signals;
 mod utils;
 
 use display_network::Network;
-use display_procs::{create_and_fill_model, Procs};
+use display_procs::{create_and_fill_model, GroupBy, Procs, COL_PID};
 use display_sysinfo::DisplaySysInfo;
+use launch_history::LaunchHistory;
+use launcher::LaunchedProcesses;
 use notebook::NoteBook;
 use settings::Settings;
-use utils::format_number;
+use signals::StoppedProcesses;
+use utils::{get_now, ProcessIdentity, StatusBar};
 
 pub const APPLICATION_NAME: &str = "fr.guillaume_gomez.ProcessViewer";
 
-fn update_window(list: &gtk::ListStore, entries: &HashMap<Pid, sysinfo::Process>) {
-    let mut seen: HashSet<Pid> = HashSet::new();
+/// Returns the PID under which the process should be displayed in the tree (if any).
+fn get_tree_parent(
+    pro: &sysinfo::Process,
+    entries: &HashMap<Pid, sysinfo::Process>,
+    tree_mode: bool,
+) -> Option<Pid> {
+    if !tree_mode {
+        return None;
+    }
+    pro.parent().filter(|ppid| {
+        *ppid != pro.pid()
+            && entries
+                .get(ppid)
+                .map(display_procs::is_displayable)
+                .unwrap_or(false)
+    })
+}
 
-    if let Some(iter) = list.get_iter_first() {
-        let mut valid = true;
-        while valid {
-            let pid = match list.get_value(&iter, 0).get::<u32>() {
-                Ok(pid) => pid,
-                _ => {
-                    valid = list.iter_next(&iter);
-                    continue;
-                }
-            };
-            if let Some(pid) = pid.map(|x| x as Pid) {
-                if let Some(p) = entries.get(&(pid)) {
-                    let disk_usage = p.disk_usage();
-                    let disk_usage = disk_usage.written_bytes + disk_usage.read_bytes;
-                    let memory = p.memory() * 1_000;
-                    list.set(
-                        &iter,
-                        &[2, 3, 4, 6, 7, 8],
-                        &[
-                            &format!("{:.1}", p.cpu_usage()),
-                            &format_number(memory),
-                            &if disk_usage > 0 {
-                                format_number(disk_usage)
-                            } else {
-                                String::new()
-                            },
-                            &p.cpu_usage(),
-                            &memory,
-                            &disk_usage,
-                        ],
-                    );
-                    valid = list.iter_next(&iter);
-                    seen.insert(pid);
-                } else {
-                    valid = list.remove(&iter);
-                }
+fn update_rows(
+    tree: &gtk::TreeStore,
+    parent: Option<&gtk::TreeIter>,
+    parent_pid: Option<Pid>,
+    entries: &HashMap<Pid, sysinfo::Process>,
+    tree_mode: bool,
+    refresh_interval: f64,
+    seen: &mut HashMap<Pid, gtk::TreeIter>,
+) {
+    let iter = match tree.iter_children(parent) {
+        Some(iter) => iter,
+        None => return,
+    };
+    let mut valid = true;
+    while valid {
+        let pid = match tree.get_value(&iter, COL_PID as _).get::<u32>() {
+            Ok(Some(pid)) => pid as Pid,
+            _ => {
+                valid = tree.iter_next(&iter);
+                continue;
             }
+        };
+        match entries.get(&pid) {
+            Some(p)
+                if !seen.contains_key(&pid)
+                    && get_tree_parent(p, entries, tree_mode) == parent_pid
+                    && display_procs::get_row_identity(tree, &iter)
+                        == Some(ProcessIdentity::new(p)) =>
+            {
+                display_procs::update_process_row(tree, &iter, p, refresh_interval);
+                update_rows(
+                    tree,
+                    Some(&iter),
+                    Some(pid),
+                    entries,
+                    tree_mode,
+                    refresh_interval,
+                    seen,
+                );
+                seen.insert(pid, iter.clone());
+                valid = tree.iter_next(&iter);
+            }
+            // The process is dead (its PID may have been reused) or has been moved to another
+            // parent: its row (and the ones of its children) will be re-created afterwards if
+            // needed.
+            _ => valid = tree.remove(&iter),
         }
     }
+}
 
-    for (pid, pro) in entries.iter() {
-        if !seen.contains(pid) {
-            create_and_fill_model(
-                list,
-                pid.as_u32(),
-                &pro.cmd(),
-                &pro.name(),
-                pro.cpu_usage(),
-                pro.memory() * 1_000,
-            );
-        }
+/// Adds the process to the tree (its parents first if needed) if it isn't already there.
+fn insert_process(
+    tree: &gtk::TreeStore,
+    pid: Pid,
+    entries: &HashMap<Pid, sysinfo::Process>,
+    tree_mode: bool,
+    refresh_interval: f64,
+    seen: &mut HashMap<Pid, gtk::TreeIter>,
+) -> Option<gtk::TreeIter> {
+    if let Some(iter) = seen.get(&pid) {
+        return Some(iter.clone());
     }
+    let pro = entries.get(&pid)?;
+    let parent = get_tree_parent(pro, entries, tree_mode)
+        .and_then(|ppid| insert_process(tree, ppid, entries, tree_mode, refresh_interval, seen));
+    let iter = create_and_fill_model(tree, parent.as_ref(), pro, refresh_interval)?;
+    seen.insert(pid, iter.clone());
+    Some(iter)
 }
 
-fn parse_quote(line: &str, quote: char) -> Vec<String> {
-    let args = line.split(quote).collect::<Vec<&str>>();
-    let mut out_args = vec![];
+/// Lists the processes under the row of their group. The rows of the processes which aren't
+/// in a group anymore (or in another one) are removed.
+fn update_groups(
+    tree: &gtk::TreeStore,
+    entries: &HashMap<Pid, sysinfo::Process>,
+    group_by: GroupBy,
+    refresh_interval: f64,
+) {
+    // Getting the group can require reading a file so it's only done once per process.
+    let process_groups = entries
+        .values()
+        .filter(|p| display_procs::is_displayable(p))
+        .map(|p| (p.pid(), group_by.get_group(p)))
+        .collect::<HashMap<_, _>>();
+    let mut groups: HashMap<String, Vec<&sysinfo::Process>> = HashMap::new();
+    for (pid, group) in &process_groups {
+        groups.entry(group.clone()).or_default().push(&entries[pid]);
+    }
 
-    for (num, arg) in args.iter().enumerate() {
-        if num != 1 {
-            out_args.extend_from_slice(&parse_entry(arg));
-        } else {
-            out_args.push((*arg).to_owned());
+    let mut group_rows: HashMap<String, gtk::TreeIter> = HashMap::new();
+    if let Some(iter) = tree.iter_children(None) {
+        let mut valid = true;
+        while valid {
+            // The process rows come from the other modes.
+            match display_procs::get_row_group(tree, &iter) {
+                Some(group) if groups.contains_key(&group) && !group_rows.contains_key(&group) => {
+                    group_rows.insert(group, iter.clone());
+                    valid = tree.iter_next(&iter);
+                }
+                _ => valid = tree.remove(&iter),
+            }
         }
     }
-    out_args
-}
 
-// super simple parsing
-fn parse_entry(line: &str) -> Vec<String> {
-    match (line.find('\''), line.find('"')) {
-        (Some(x), Some(y)) => {
-            if x < y {
-                parse_quote(line, '\'')
-            } else {
-                parse_quote(line, '"')
+    for (group, processes) in &groups {
+        let group_iter = group_rows
+            .remove(group)
+            .unwrap_or_else(|| display_procs::create_group_row(tree, group));
+        let mut seen: HashMap<Pid, gtk::TreeIter> = HashMap::new();
+        if let Some(iter) = tree.iter_children(Some(&group_iter)) {
+            let mut valid = true;
+            while valid {
+                let identity = display_procs::get_row_identity(tree, &iter);
+                match identity.as_ref().and_then(|i| entries.get(&i.pid)) {
+                    Some(p)
+                        if !seen.contains_key(&p.pid())
+                            && identity == Some(ProcessIdentity::new(p))
+                            && process_groups.get(&p.pid()) == Some(group) =>
+                    {
+                        display_procs::update_process_row(tree, &iter, p, refresh_interval);
+                        seen.insert(p.pid(), iter.clone());
+                        valid = tree.iter_next(&iter);
+                    }
+                    _ => valid = tree.remove(&iter),
+                }
             }
         }
-        (Some(_), None) => parse_quote(line, '\''),
-        (None, Some(_)) => parse_quote(line, '"'),
-        (None, None) => line
-            .split(' ')
-            .map(::std::borrow::ToOwned::to_owned)
-            .collect::<Vec<String>>(),
+        for pro in processes {
+            if !seen.contains_key(&pro.pid()) {
+                create_and_fill_model(tree, Some(&group_iter), pro, refresh_interval);
+            }
+        }
+        display_procs::update_group_row(tree, &group_iter, group);
     }
 }
 
-#[cfg(unix)]
-fn build_command(c: &mut Command) -> &mut Command {
-    unsafe {
-        c.pre_exec(|| {
-            libc::setsid();
-            Ok(())
-        })
+/// `refresh_interval` is the number of seconds since the previous refresh of the processes.
+fn update_window(
+    tree: &gtk::TreeStore,
+    entries: &HashMap<Pid, sysinfo::Process>,
+    tree_mode: bool,
+    group_by: Option<GroupBy>,
+    refresh_interval: f64,
+) {
+    if let Some(group_by) = group_by {
+        update_groups(tree, entries, group_by, refresh_interval);
+        display_procs::update_subtree_totals(tree, None);
+        return;
     }
-}
+    let mut seen: HashMap<Pid, gtk::TreeIter> = HashMap::new();
 
-#[cfg(windows)]
-fn build_command(c: &mut Command) -> &mut Command {
-    c
-}
-
-fn start_detached_process(line: &str) -> Option<String> {
-    let args = parse_entry(line);
-    let command = args[0].clone();
-
-    let cmd = build_command(Command::new(&command).args(&args))
-        .stdin(Stdio::null())
-        .stderr(Stdio::null())
-        .stdout(Stdio::null())
-        .spawn();
-    if cmd.is_err() {
-        Some(format!("Failed to start '{}'", &command))
-    } else {
-        None
+    update_rows(
+        tree,
+        None,
+        None,
+        entries,
+        tree_mode,
+        refresh_interval,
+        &mut seen,
+    );
+    for pid in entries.keys() {
+        insert_process(tree, *pid, entries, tree_mode, refresh_interval, &mut seen);
     }
+    display_procs::update_subtree_totals(tree, None);
 }
 
-fn run_command<T: IsA<gtk::Window>>(input: &Entry, window: &T, d: &Dialog) {
-    if let Some(text) = input.get_text() {
-        let x = if let Some(x) = start_detached_process(&text) {
-            x
-        } else {
-            "The command started successfully".to_owned()
-        };
-        d.close();
-        let m = MessageDialog::new(
-            Some(window),
-            gtk::DialogFlags::DESTROY_WITH_PARENT,
-            gtk::MessageType::Info,
-            gtk::ButtonsType::Ok,
-            &x,
-        );
-        m.set_modal(true);
-        m.connect_response(|dialog, response| {
-            if response == gtk::ResponseType::DeleteEvent
-                || response == gtk::ResponseType::Close
-                || response == gtk::ResponseType::Ok
-            {
-                dialog.close();
-            }
-        });
-        m.show_all();
+/// Refreshes the process list and keeps its sorting and selection.
+fn refresh_process_list(
+    tree_view: &gtk::TreeView,
+    tree: &gtk::TreeStore,
+    current_pids: &Rc<RefCell<HashMap<Pid, ProcessIdentity>>>,
+    entries: &HashMap<Pid, sysinfo::Process>,
+    tree_mode: bool,
+    group_by: Option<GroupBy>,
+    refresh_interval: f64,
+) {
+    let selected = current_pids.borrow().clone();
+
+    // first part, deactivate sorting
+    let sorted = TreeSortableExtManual::get_sort_column_id(tree);
+    tree.set_unsorted();
+
+    // we update the tree view
+    update_window(tree, entries, tree_mode, group_by, refresh_interval);
+
+    // we re-enable the sorting
+    if let Some((col, order)) = sorted {
+        tree.set_sort_column_id(col, order);
     }
+
+    display_procs::restore_selection(tree_view, &selected);
 }
 
 fn create_new_proc_diag(
@@ -246,11 +318,19 @@ fn create_new_proc_diag(
 
 pub struct RequiredForSettings {
     process_refresh_timeout: Arc<Mutex<u32>>,
+    /// The number of seconds between the two last refreshes of the processes.
+    process_refresh_interval: Rc<Cell<f64>>,
     network_refresh_timeout: Arc<Mutex<u32>>,
     system_refresh_timeout: Arc<Mutex<u32>>,
     sys: Arc<Mutex<sysinfo::System>>,
     process_dialogs: Rc<RefCell<Vec<process_dialog::ProcDialog>>>,
-    list_store: gtk::ListStore,
+    process_tree_view: gtk::TreeView,
+    tree_store: gtk::TreeStore,
+    tree_mode: Rc<Cell<bool>>,
+    group_by: Rc<Cell<Option<GroupBy>>>,
+    current_pids: Rc<RefCell<HashMap<Pid, ProcessIdentity>>>,
+    launched_processes: Rc<RefCell<LaunchedProcesses>>,
+    stopped_processes: Rc<RefCell<StoppedProcesses>>,
     display_tab: Rc<RefCell<DisplaySysInfo>>,
     network_tab: Rc<RefCell<Network>>,
 }
@@ -261,45 +341,62 @@ fn setup_timeout(rfs: &Rc<RefCell<RequiredForSettings>>) {
 
     let sys = &rfs.sys;
     let process_dialogs = &rfs.process_dialogs;
-    let list_store = &rfs.list_store;
+    let process_tree_view = &rfs.process_tree_view;
+    let tree_store = &rfs.tree_store;
+    let tree_mode = &rfs.tree_mode;
+    let group_by = &rfs.group_by;
+    let current_pids = &rfs.current_pids;
     let process_refresh_timeout = &rfs.process_refresh_timeout;
+    let process_refresh_interval = &rfs.process_refresh_interval;
+    let launched_processes = &rfs.launched_processes;
+    let stopped_processes = &rfs.stopped_processes;
 
     thread::spawn(
         clone!(@weak sys, @strong ready_tx, @weak process_refresh_timeout => move || {
+            let mut last_refresh = Instant::now();
             loop {
                 let sleep_dur = Duration::from_millis(
                     *process_refresh_timeout.lock().expect("failed to lock process refresh mutex") as _);
                 thread::sleep(sleep_dur);
                 sys.lock().expect("failed to lock to refresh processes").refresh_processes();
-                ready_tx.send(false).expect("failed to send data through process refresh channel");
+                // The I/O rates are computed from the real time elapsed between two refreshes.
+                let interval = last_refresh.elapsed().as_secs_f64();
+                last_refresh = Instant::now();
+                ready_tx.send(interval).expect("failed to send data through process refresh channel");
             }
         }),
     );
 
     ready_rx.attach(None,
-        clone!(@weak sys, @weak list_store, @weak process_dialogs => @default-panic, move |_: bool| {
-        // first part, deactivate sorting
-        let sorted = TreeSortableExtManual::get_sort_column_id(&list_store);
-        list_store.set_unsorted();
-
+        clone!(@weak sys, @weak process_tree_view, @weak tree_store, @weak tree_mode, @weak group_by, @weak current_pids, @weak process_dialogs, @weak process_refresh_interval, @weak launched_processes, @weak stopped_processes => @default-panic, move |interval: f64| {
         let mut to_remove = 0;
         let start_time = get_now();
         let mut dialogs = process_dialogs.borrow_mut();
 
+        process_refresh_interval.set(interval);
+        let mut launched_processes = launched_processes.borrow_mut();
         if let Ok(sys) = sys.lock() {
-            // we update the tree view
-            update_window(&list_store, sys.get_processes());
-
-            // we re-enable the sorting
-            if let Some((col, order)) = sorted {
-                list_store.set_sort_column_id(col, order);
-            }
+            launched_processes.check_exited(&sys);
+            stopped_processes.borrow_mut().update(&sys);
+            refresh_process_list(
+                &process_tree_view,
+                &tree_store,
+                &current_pids,
+                sys.get_processes(),
+                tree_mode.get(),
+                group_by.get(),
+                interval,
+            );
             for dialog in dialogs.iter_mut().filter(|x| !x.is_dead) {
-                // TODO: check if the process name matches the PID too!
-                if let Some(process) = sys.get_processes().get(&dialog.pid) {
-                    dialog.update(process, start_time);
-                } else {
-                    dialog.set_dead();
+                match sys.get_processes().get(&dialog.pid) {
+                    // If the PID has been reused, the process we were displaying is dead.
+                    Some(process) if dialog.identity.is_same_process(process) => {
+                        dialog.update(process, start_time, interval);
+                    }
+                    _ => {
+                        let exit_status = launched_processes.take_exit_status(&dialog.identity);
+                        dialog.set_dead(exit_status);
+                    }
                 }
                 if dialog.need_remove() {
                     to_remove += 1;
@@ -377,11 +474,242 @@ fn setup_system_timeout(rfs: &Rc<RefCell<RequiredForSettings>>, settings: &Rc<Re
     );
 }
 
-fn get_now() -> u64 {
-    SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .expect("couldn't get start time")
-        .as_secs()
+fn send_signal(
+    sys: &Arc<Mutex<sysinfo::System>>,
+    settings: &Rc<RefCell<Settings>>,
+    targets: &[ProcessIdentity],
+    signal: libc::c_int,
+    stopped: &RefCell<StoppedProcesses>,
+    status_bar: &StatusBar,
+) {
+    let sys = sys.lock().expect("failed to lock to send a signal");
+    match signals::signal_processes(
+        &sys,
+        &settings.borrow(),
+        targets,
+        signal,
+        &mut stopped.borrow_mut(),
+    ) {
+        Ok(msg) => status_bar.show_message(gtk::MessageType::Info, &msg),
+        Err(msg) => status_bar.show_message(gtk::MessageType::Error, &msg),
+    }
+}
+
+/// Shows the processes which are about to receive `signal` and only sends it once the user
+/// confirmed.
+fn confirm_and_send_signal(
+    window: &gtk::ApplicationWindow,
+    sys: &Arc<Mutex<sysinfo::System>>,
+    settings: &Rc<RefCell<Settings>>,
+    processes: Vec<ProcessIdentity>,
+    signal: libc::c_int,
+    stopped: &Rc<RefCell<StoppedProcesses>>,
+    status_bar: &StatusBar,
+) {
+    let targets = {
+        let sys = sys.lock().expect("failed to lock to get signal targets");
+        let settings = settings.borrow();
+        processes
+            .iter()
+            .filter_map(|identity| {
+                sys.get_process(identity.pid)
+                    .filter(|p| identity.is_same_process(p))
+            })
+            .map(|p| signals::SignalTarget::new(p, &settings))
+            .collect::<Vec<_>>()
+    };
+    if targets.is_empty() || targets.iter().all(|t| t.is_protected) {
+        // No need to ask anything, we can directly report the error(s).
+        send_signal(sys, settings, &processes, signal, stopped, status_bar);
+        return;
+    }
+    signals::show_signal_confirmation_dialog(
+        window,
+        signal,
+        &targets,
+        clone!(@weak sys, @weak settings, @weak stopped, @strong status_bar => move || {
+            // The processes are checked again when sending the signal since they could have
+            // died (and their PID been reused) while the dialog was opened.
+            send_signal(&sys, &settings, &processes, signal, &stopped, &status_bar);
+        }),
+    );
+}
+
+/// Returns the selected processes, sorted by PID.
+fn get_selected_processes(
+    current_pids: &Rc<RefCell<HashMap<Pid, ProcessIdentity>>>,
+) -> Vec<ProcessIdentity> {
+    let mut processes = current_pids.borrow().values().cloned().collect::<Vec<_>>();
+    processes.sort_by_key(|p| p.pid);
+    processes
+}
+
+fn setup_signal_actions(
+    application: &gtk::Application,
+    window: &gtk::ApplicationWindow,
+    procs: &Procs,
+    sys: &Arc<Mutex<sysinfo::System>>,
+    settings: &Rc<RefCell<Settings>>,
+    process_dialogs: &Rc<RefCell<Vec<process_dialog::ProcDialog>>>,
+    stopped: &Rc<RefCell<StoppedProcesses>>,
+) {
+    let current_pids = &procs.current_pids;
+    let status_bar = &procs.status_bar;
+
+    procs.kill_button.connect_clicked(
+        clone!(@weak current_pids, @weak sys, @weak settings, @weak window, @weak stopped, @strong status_bar => move |_| {
+            let processes = get_selected_processes(&current_pids);
+            confirm_and_send_signal(&window, &sys, &settings, processes, signals::SIGKILL, &stopped, &status_bar);
+        }),
+    );
+    // Pausing and resuming can be undone so there is no need to ask for a confirmation.
+    for &(button, signal) in &[
+        (&procs.pause_button, signals::SIGSTOP),
+        (&procs.resume_button, signals::SIGCONT),
+    ] {
+        button.connect_clicked(
+            clone!(@weak current_pids, @weak sys, @weak settings, @weak stopped, @strong status_bar => move |_| {
+                let processes = get_selected_processes(&current_pids);
+                send_signal(&sys, &settings, &processes, signal, &stopped, &status_bar);
+            }),
+        );
+    }
+
+    let send_signal_action = gio::SimpleAction::new(
+        "send-signal",
+        Some(glib::VariantTy::new("i").expect("invalid variant type")),
+    );
+    send_signal_action.connect_activate(
+        clone!(@weak current_pids, @weak sys, @weak settings, @weak window, @weak stopped, @strong status_bar => move |_, signal| {
+            if let Some(signal) = signal.and_then(|s| s.get::<i32>()) {
+                let processes = get_selected_processes(&current_pids);
+                confirm_and_send_signal(&window, &sys, &settings, processes, signal, &stopped, &status_bar);
+            }
+        }),
+    );
+
+    let send_custom_signal = gio::SimpleAction::new("send-custom-signal", None);
+    send_custom_signal.connect_activate(
+        clone!(@weak current_pids, @weak sys, @weak settings, @weak window, @weak stopped, @strong status_bar => move |_, _| {
+            let processes = get_selected_processes(&current_pids);
+            if !processes.is_empty() {
+                // The value can be the one of a dangerous signal (like `SIGKILL`) so it has to be
+                // confirmed as well.
+                signals::show_custom_signal_dialog(&window, clone!(@weak sys, @weak settings, @weak window, @weak stopped, @strong status_bar => move |signal| {
+                    confirm_and_send_signal(&window, &sys, &settings, processes.clone(), signal, &stopped, &status_bar);
+                }));
+            }
+        }),
+    );
+
+    let kill_subtree = gio::SimpleAction::new("kill-subtree", None);
+    kill_subtree.connect_activate(
+        clone!(@weak current_pids, @weak sys, @weak settings, @weak window, @weak stopped, @strong status_bar => move |_, _| {
+            let mut processes: Vec<ProcessIdentity> = Vec::new();
+            {
+                let sys = sys.lock().expect("failed to lock to get process subtree");
+                let mut pids = Vec::new();
+                for selected in get_selected_processes(&current_pids) {
+                    // We don't want to kill the children of another process which got its PID.
+                    match sys.get_process(selected.pid) {
+                        Some(p) if selected.is_same_process(p) => {}
+                        _ => {
+                            processes.push(selected);
+                            continue;
+                        }
+                    }
+                    // A selected process can be a descendant of another selected one.
+                    signals::merge_subtree(&mut pids, signals::get_process_subtree(&sys, selected.pid));
+                }
+                for pid in pids {
+                    if processes.iter().any(|p| p.pid == pid) {
+                        continue;
+                    }
+                    if let Some(process) = sys.get_process(pid) {
+                        processes.push(ProcessIdentity::new(process));
+                    }
+                }
+            }
+            confirm_and_send_signal(&window, &sys, &settings, processes, signals::SIGKILL, &stopped, &status_bar);
+        }),
+    );
+
+    // Used by the process dialogs and the "Stopped processes" menu. The process is identified by
+    // its PID so its identity has to be retrieved from one of them.
+    for &(name, signal) in &[
+        ("pause-process", signals::SIGSTOP),
+        ("resume-process", signals::SIGCONT),
+    ] {
+        let action = gio::SimpleAction::new(
+            name,
+            Some(glib::VariantTy::new("i").expect("invalid variant type")),
+        );
+        action.connect_activate(
+            clone!(@weak sys, @weak settings, @weak process_dialogs, @weak stopped, @strong status_bar => move |_, pid| {
+                let pid = match pid.and_then(|pid| pid.get::<i32>()) {
+                    Some(pid) => pid as Pid,
+                    None => return,
+                };
+                let dialogs = process_dialogs.borrow();
+                let dialog = dialogs.iter().find(|d| d.pid == pid && !d.is_dead);
+                let identity = match dialog {
+                    Some(dialog) => Some(dialog.identity.clone()),
+                    None => stopped.borrow().get(pid).cloned(),
+                };
+                if let Some(identity) = identity {
+                    let status_bar = dialog.map(|d| &d.status_bar).unwrap_or(&status_bar);
+                    send_signal(&sys, &settings, &[identity], signal, &stopped, status_bar);
+                }
+            }),
+        );
+        application.add_action(&action);
+    }
+
+    let resume_all = gio::SimpleAction::new("resume-all", None);
+    resume_all.connect_activate(
+        clone!(@weak sys, @weak settings, @weak stopped, @strong status_bar => move |_, _| {
+            let processes = stopped.borrow().get_all();
+            send_signal(&sys, &settings, &processes, signals::SIGCONT, &stopped, &status_bar);
+        }),
+    );
+
+    let renice = gio::SimpleAction::new("renice", None);
+    renice.connect_activate(
+        clone!(@weak current_pids, @weak window, @strong status_bar => move |_, _| {
+            let pids = get_selected_processes(&current_pids)
+                .iter()
+                .map(|p| p.pid)
+                .collect::<Vec<_>>();
+            let current = match pids.first() {
+                Some(pid) => scheduling::get_priority(*pid).unwrap_or(0),
+                None => return,
+            };
+            scheduling::show_renice_dialog(&window, current, clone!(@strong status_bar => move |nice| {
+                match scheduling::set_priorities(&pids, nice) {
+                    Ok(msg) => status_bar.show_message(gtk::MessageType::Info, &msg),
+                    Err(msg) => status_bar.show_message(gtk::MessageType::Error, &msg),
+                }
+            }));
+        }),
+    );
+
+    application.add_action(&send_signal_action);
+    application.add_action(&send_custom_signal);
+    application.add_action(&kill_subtree);
+    application.add_action(&resume_all);
+    application.add_action(&renice);
+}
+
+/// Stores the current layout of the process and network lists so it can be restored on startup.
+fn save_columns_settings(
+    settings: &RefCell<Settings>,
+    process_tree: &gtk::TreeView,
+    network_tree: &gtk::TreeView,
+) {
+    let mut settings = settings.borrow_mut();
+    settings.process_columns = columns::get_tree_view_settings(process_tree);
+    settings.network_columns = columns::get_tree_view_settings(network_tree);
+    settings.save();
 }
 
 fn build_ui(application: &gtk::Application) {
@@ -391,13 +719,16 @@ fn build_ui(application: &gtk::Application) {
     let menu_bar = gio::Menu::new();
     let more_menu = gio::Menu::new();
     let settings_menu = gio::Menu::new();
+    let profiles_menu = gio::Menu::new();
+    let stopped_menu = gio::Menu::new();
+    let launch_history = Rc::new(RefCell::new(LaunchHistory::load()));
 
     menu.append(Some("Launch new executable"), Some("app.new-task"));
+    launch_history.borrow().fill_profiles_menu(&profiles_menu);
+    menu.append_submenu(Some("Run launch profile"), &profiles_menu);
+    menu.append_submenu(Some("Stopped processes"), &stopped_menu);
     menu.append(Some("Quit"), Some("app.quit"));
     let quit = gio::SimpleAction::new("quit", None);
-    quit.connect_activate(clone!(@weak application => move |_,_| {
-        application.quit();
-    }));
     application.set_accels_for_action("app.quit", &["<Primary>Q"]);
 
     settings_menu.append(Some("Display temperature in °F"), Some("app.temperature"));
@@ -416,9 +747,11 @@ fn build_ui(application: &gtk::Application) {
     let mut sys = sysinfo::System::new_all();
     let start_time = get_now();
     let mut note = NoteBook::new();
-    let procs = Procs::new(sys.get_processes(), &mut note, &window);
-    let current_pid = Rc::clone(&procs.current_pid);
+    let refresh_interval = f64::from(settings.refresh_processes_rate) / 1000.;
+    let procs = Procs::new(sys.get_processes(), &mut note, &window, refresh_interval);
+    let current_pids = Rc::clone(&procs.current_pids);
     let info_button = procs.info_button.clone();
+    let tree_button = procs.tree_button.clone();
 
     window.set_title("Process viewer");
     window.set_position(gtk::WindowPosition::Center);
@@ -431,18 +764,21 @@ fn build_ui(application: &gtk::Application) {
 
     sys.refresh_all();
     let sys = Arc::new(Mutex::new(sys));
-    procs
-        .kill_button
-        .connect_clicked(clone!(@weak current_pid, @weak sys => move |_| {
-            let sys = sys.lock().expect("failed to lock to kill a process");
-            if let Some(process) = current_pid.get().and_then(|pid| sys.get_process(pid)) {
-                process.kill(Signal::Kill);
-            }
-        }));
-
     let display_tab = DisplaySysInfo::new(&sys, &mut note, &settings);
 
     let settings = Rc::new(RefCell::new(settings));
+    let process_dialogs: Rc<RefCell<Vec<process_dialog::ProcDialog>>> =
+        Rc::new(RefCell::new(Vec::new()));
+    let stopped_processes = Rc::new(RefCell::new(StoppedProcesses::new(stopped_menu)));
+    setup_signal_actions(
+        application,
+        &window,
+        &procs,
+        &sys,
+        &settings,
+        &process_dialogs,
+        &stopped_processes,
+    );
     let network_tab = Rc::new(RefCell::new(Network::new(&mut note, &window, &sys)));
     display_disk::create_disk_info(&sys, &mut note);
 
@@ -455,17 +791,26 @@ fn build_ui(application: &gtk::Application) {
 
     window.add(&v_box);
 
-    let process_dialogs: Rc<RefCell<Vec<process_dialog::ProcDialog>>> =
-        Rc::new(RefCell::new(Vec::new()));
-    let list_store = procs.list_store.clone();
+    let process_tree_view = procs.left_tree.clone();
+    let tree_store = procs.tree_store.clone();
+    let tree_mode = procs.tree_mode.clone();
+    let group_by = procs.group_by.clone();
 
+    let launched_processes = Rc::new(RefCell::new(LaunchedProcesses::default()));
     let rfs = Rc::new(RefCell::new(RequiredForSettings {
         process_refresh_timeout: Arc::new(Mutex::new(settings.borrow().refresh_processes_rate)),
+        process_refresh_interval: Rc::new(Cell::new(refresh_interval)),
         network_refresh_timeout: Arc::new(Mutex::new(settings.borrow().refresh_network_rate)),
         system_refresh_timeout: Arc::new(Mutex::new(settings.borrow().refresh_system_rate)),
         sys: sys.clone(),
         process_dialogs: process_dialogs.clone(),
-        list_store,
+        process_tree_view,
+        tree_store,
+        tree_mode,
+        group_by: group_by.clone(),
+        current_pids: current_pids.clone(),
+        launched_processes: launched_processes.clone(),
+        stopped_processes,
         display_tab,
         network_tab: network_tab.clone(),
     }));
@@ -479,10 +824,64 @@ fn build_ui(application: &gtk::Application) {
         settings::show_settings_dialog(&settings, &rfs);
     }));
 
+    // The tree structure is updated right away instead of waiting for the next refresh.
+    let refresh_interval = Rc::clone(&rfs.borrow().process_refresh_interval);
+    tree_button.connect_toggled(
+        clone!(@weak procs.tree_store as tree_store, @weak procs.left_tree as left_tree, @weak current_pids, @weak group_by, @weak sys, @weak refresh_interval => move |button| {
+            let sys = sys.lock().expect("failed to lock to update process tree");
+            refresh_process_list(&left_tree, &tree_store, &current_pids, sys.get_processes(), button.get_active(), group_by.get(), refresh_interval.get());
+            if button.get_active() {
+                left_tree.expand_all();
+            }
+        }),
+    );
+    procs.group_combo.connect_changed(
+        clone!(@weak procs.tree_store as tree_store, @weak procs.left_tree as left_tree, @weak current_pids, @weak procs.tree_mode as tree_mode, @weak group_by, @weak sys, @weak refresh_interval => move |_| {
+            let sys = sys.lock().expect("failed to lock to update process groups");
+            refresh_process_list(&left_tree, &tree_store, &current_pids, sys.get_processes(), tree_mode.get(), group_by.get(), refresh_interval.get());
+        }),
+    );
+
+    columns::restore_tree_view_settings(&procs.left_tree, &settings.borrow().process_columns);
+    columns::restore_tree_view_settings(
+        &network_tab.borrow().tree,
+        &settings.borrow().network_columns,
+    );
+    tree_button.set_active(settings.borrow().process_tree_mode);
+    tree_button.connect_toggled(clone!(@weak settings => move |button| {
+        settings.borrow_mut().process_tree_mode = button.get_active();
+        settings.borrow().save();
+    }));
+    procs
+        .group_combo
+        .set_active_id(Some(display_procs::get_group_by_id(
+            settings.borrow().process_group_by,
+        )));
+    procs
+        .group_combo
+        .connect_changed(clone!(@weak settings, @weak group_by => move |_| {
+            settings.borrow_mut().process_group_by = group_by.get();
+            settings.borrow().save();
+        }));
+
+    quit.connect_activate(
+        clone!(@weak application, @weak settings, @weak procs.left_tree as left_tree, @weak network_tab => move |_,_| {
+            save_columns_settings(&settings, &left_tree, &network_tab.borrow().tree);
+            application.quit();
+        }),
+    );
+    window.connect_delete_event(
+        clone!(@weak settings, @weak procs.left_tree as left_tree, @weak network_tab => @default-return Inhibit(false), move |_, _| {
+            save_columns_settings(&settings, &left_tree, &network_tab.borrow().tree);
+            Inhibit(false)
+        }),
+    );
+
     info_button.connect_clicked(
-        clone!(@weak current_pid, @weak process_dialogs, @weak sys => move |_| {
-                if let Some(pid) = current_pid.get() {
-                    create_new_proc_diag(&process_dialogs, pid, &*sys.lock().expect("failed to lock to create new proc dialog"), start_time);
+        clone!(@weak current_pids, @weak process_dialogs, @weak sys => move |_| {
+                let sys = sys.lock().expect("failed to lock to create new proc dialog");
+                for pid in current_pids.borrow().keys() {
+                    create_new_proc_diag(&process_dialogs, *pid, &sys, start_time);
                 }
             }
         ),
@@ -493,11 +892,18 @@ fn build_ui(application: &gtk::Application) {
         .connect_row_activated(clone!(@weak sys => move |tree_view, path, _| {
                 let model = tree_view.get_model().expect("couldn't get model");
                 let iter = model.get_iter(path).expect("couldn't get iter");
-                let pid = model.get_value(&iter, 0)
-                               .get::<u32>()
-                               .expect("Model::get failed")
-                               .map(|x| x as Pid)
-                               .expect("failed to get value from model");
+                let pid = match display_procs::get_row_identity(&model, &iter) {
+                    Some(identity) => identity.pid,
+                    // A group row: its processes are displayed (or hidden) instead.
+                    None => {
+                        if tree_view.row_expanded(path) {
+                            tree_view.collapse_row(path);
+                        } else {
+                            tree_view.expand_row(path, false);
+                        }
+                        return;
+                    }
+                };
                 create_new_proc_diag(&process_dialogs, pid, &*sys.lock().expect("failed to lock to create new proc dialog (from tree)"), start_time);
             }
         ));
@@ -530,62 +936,23 @@ fn build_ui(application: &gtk::Application) {
     }));
 
     let new_task = gio::SimpleAction::new("new-task", None);
-    new_task.connect_activate(clone!(@weak window => move |_, _| {
-        let dialog = gtk::Dialog::new_with_buttons(
-            Some("Launch new executable"),
-            Some(&window),
-            gtk::DialogFlags::USE_HEADER_BAR,
-            &[("Run", gtk::ResponseType::Other(0)), ("Cancel", gtk::ResponseType::Close)],
-        );
-        let input = Entry::new();
-
-        // To set "run" button disabled by default.
-        dialog.set_response_sensitive(gtk::ResponseType::Other(0), false);
-        // To make "run" and "cancel" button take all spaces.
-        if let Some(run) = dialog.get_widget_for_response(gtk::ResponseType::Other(0)) {
-            if let Some(parent) = run.get_parent() {
-                match parent.downcast::<gtk::ButtonBox>() {
-                    Ok(parent) => parent.set_property_layout_style(gtk::ButtonBoxStyle::Expand),
-                    Err(e) => {
-                        eprintln!(
-                            "<Process_Viewer::build_ui> Failed to set layout style for new task \
-                             button box: {}", e)
-                    }
-                }
-            }
-        }
-        input.connect_changed(clone!(@weak dialog => move |input| {
-            match input.get_text() {
-                Some(ref x) if !x.is_empty() => {
-                    dialog.set_response_sensitive(gtk::ResponseType::Other(0), true);
-                }
-                _ => dialog.set_response_sensitive(gtk::ResponseType::Other(0), false),
-            }
-        }));
-        input.connect_activate(clone!(@weak window, @weak dialog => move |input| {
-            run_command(input, &window, &dialog);
-        }));
-        dialog.connect_response(clone!(@weak input, @weak window => move |dialog, response| {
-            match response {
-                gtk::ResponseType::Close => {
-                    dialog.close();
-                }
-                gtk::ResponseType::Other(0) => {
-                    run_command(&input, &window, &dialog);
-                }
-                _ => {}
-            }
-        }));
+    new_task.connect_activate(
+        clone!(@weak window, @weak launched_processes, @weak launch_history, @strong profiles_menu => move |_, _| {
+            launcher::show_launch_dialog(&window, &launched_processes, &launch_history, &profiles_menu);
+        }),
+    );
 
-        dialog.get_content_area().add(&input);
-        // To silence the annying warning:
-        // "(.:2257): Gtk-WARNING **: Allocating size to GtkWindow 0x7f8a31038290 without
-        // calling gtk_widget_get_preferred_width/height(). How does the code know the size to
-        // allocate?"
-        dialog.get_preferred_width();
-        dialog.set_size_request(400, 70);
-        dialog.show_all();
-    }));
+    let run_profile = gio::SimpleAction::new(
+        "run-profile",
+        Some(glib::VariantTy::new("s").expect("invalid variant type")),
+    );
+    run_profile.connect_activate(
+        clone!(@weak window, @weak launched_processes, @weak launch_history => move |_, name| {
+            if let Some(name) = name.and_then(|name| name.get::<String>()) {
+                launcher::run_profile(&window, &name, &launched_processes, &launch_history);
+            }
+        }),
+    );
 
     let graphs = gio::SimpleAction::new_stateful(
         "graphs",
@@ -630,6 +997,7 @@ fn build_ui(application: &gtk::Application) {
     application.add_action(&temperature);
     application.add_action(&settings_action);
     application.add_action(&new_task);
+    application.add_action(&run_profile);
     application.add_action(&quit);
 
     window.set_widget_name(utils::MAIN_WINDOW_NAME);
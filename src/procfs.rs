@@ -0,0 +1,1177 @@
+//! Information about a process which isn't provided by `sysinfo`. It's read from `/proc` so it's
+//! only available on Linux.
+
+use sysinfo::Pid;
+
+#[cfg(target_os = "linux")]
+use std::collections::HashSet;
+#[cfg(target_os = "linux")]
+use std::fs;
+use std::io;
+#[cfg(target_os = "linux")]
+use std::net::{IpAddr, SocketAddr};
+#[cfg(target_os = "linux")]
+use std::path::Path;
+use std::path::PathBuf;
+
+pub struct ThreadInfo {
+    pub tid: Pid,
+    pub name: String,
+    pub state: String,
+    /// Time spent in user and kernel mode, in clock ticks.
+    pub cpu_time: u64,
+    /// The processor the thread last ran on.
+    pub processor: Option<u32>,
+}
+
+#[cfg(target_os = "linux")]
+fn get_state_name(state: &str) -> &str {
+    match state {
+        "R" => "Running",
+        "S" => "Sleeping",
+        "D" => "Disk sleep",
+        "Z" => "Zombie",
+        "T" => "Stopped",
+        "t" => "Tracing stop",
+        "X" | "x" => "Dead",
+        "I" => "Idle",
+        "W" => "Waking",
+        "P" => "Parked",
+        s => s,
+    }
+}
+
+/// Parses the content of a `stat` file and returns the name and the fields following it (the
+/// first one being the state). The name is between parentheses and can contain spaces or
+/// parentheses itself so we look for the last closing one.
+#[cfg(target_os = "linux")]
+fn parse_stat(content: &str) -> Option<(&str, Vec<&str>)> {
+    let start = content.find('(')?;
+    let end = content.rfind(')')?;
+    if end < start {
+        return None;
+    }
+    Some((
+        &content[start + 1..end],
+        content[end + 1..].split_whitespace().collect(),
+    ))
+}
+
+/// Returns the number of clock ticks per second, used by the times in the `stat` files.
+#[cfg(unix)]
+pub fn get_clock_ticks() -> u64 {
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks > 0 {
+        ticks as u64
+    } else {
+        100
+    }
+}
+
+#[cfg(windows)]
+pub fn get_clock_ticks() -> u64 {
+    100
+}
+
+#[cfg(target_os = "linux")]
+fn get_thread(pid: Pid, tid: Pid) -> Option<ThreadInfo> {
+    let content = fs::read_to_string(format!("/proc/{}/task/{}/stat", pid, tid)).ok()?;
+    let (name, fields) = parse_stat(&content)?;
+    // The fields are numbered from 1 in the proc man page and we start from the 3rd one.
+    let get = |nb: usize| fields.get(nb - 3).and_then(|f| f.parse::<u64>().ok());
+
+    Some(ThreadInfo {
+        tid,
+        name: name.to_owned(),
+        state: get_state_name(fields.first()?).to_owned(),
+        cpu_time: get(14).unwrap_or(0) + get(15).unwrap_or(0),
+        processor: get(39).map(|p| p as u32),
+    })
+}
+
+/// Lists the threads of the process (including the main one).
+#[cfg(target_os = "linux")]
+pub fn get_threads(pid: Pid) -> io::Result<Vec<ThreadInfo>> {
+    let mut threads = Vec::new();
+
+    for entry in fs::read_dir(format!("/proc/{}/task", pid))? {
+        let tid = match entry?
+            .file_name()
+            .to_str()
+            .and_then(|t| t.parse::<Pid>().ok())
+        {
+            Some(tid) => tid,
+            None => continue,
+        };
+        // The thread might have ended in the meantime.
+        if let Some(thread) = get_thread(pid, tid) {
+            threads.push(thread);
+        }
+    }
+    Ok(threads)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_threads(_pid: Pid) -> io::Result<Vec<ThreadInfo>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+pub struct FileDescriptor {
+    pub fd: u32,
+    /// "file", "socket", "pipe", etc.
+    pub kind: &'static str,
+    pub target: String,
+    /// The flags used to open the file (`O_RDONLY`, `O_APPEND`...).
+    pub flags: Option<i32>,
+    pub position: Option<u64>,
+}
+
+#[cfg(target_os = "linux")]
+fn get_file_descriptor_kind(target: &str) -> &'static str {
+    if target.starts_with("socket:") {
+        "socket"
+    } else if target.starts_with("pipe:") {
+        "pipe"
+    } else if target == "anon_inode:[eventfd]" {
+        "eventfd"
+    } else if target.starts_with("anon_inode:") {
+        "anon inode"
+    } else if target.starts_with("/dev/") {
+        "device"
+    } else if target.starts_with('/') {
+        "file"
+    } else {
+        "other"
+    }
+}
+
+/// Returns the flags and the position from the content of `/proc/<pid>/fdinfo/<fd>`.
+#[cfg(target_os = "linux")]
+fn parse_fdinfo(content: &str) -> (Option<i32>, Option<u64>) {
+    let mut flags = None;
+    let mut position = None;
+
+    for line in content.lines() {
+        let mut parts = line.splitn(2, ':');
+        match (parts.next(), parts.next().map(|v| v.trim())) {
+            // The flags are written in octal.
+            (Some("flags"), Some(v)) => flags = i32::from_str_radix(v, 8).ok(),
+            (Some("pos"), Some(v)) => position = v.parse().ok(),
+            _ => {}
+        }
+    }
+    (flags, position)
+}
+
+#[cfg(target_os = "linux")]
+fn get_file_descriptor(pid: Pid, fd: u32) -> Option<FileDescriptor> {
+    let target = fs::read_link(format!("/proc/{}/fd/{}", pid, fd)).ok()?;
+    let target = target.to_string_lossy().into_owned();
+    let (flags, position) = fs::read_to_string(format!("/proc/{}/fdinfo/{}", pid, fd))
+        .map(|info| parse_fdinfo(&info))
+        .unwrap_or((None, None));
+
+    Some(FileDescriptor {
+        fd,
+        kind: get_file_descriptor_kind(&target),
+        target,
+        flags,
+        position,
+    })
+}
+
+/// Lists the open file descriptors of the process, sorted by number.
+#[cfg(target_os = "linux")]
+pub fn get_file_descriptors(pid: Pid) -> io::Result<Vec<FileDescriptor>> {
+    let mut fds = Vec::new();
+
+    for entry in fs::read_dir(format!("/proc/{}/fd", pid))? {
+        let fd = match entry?
+            .file_name()
+            .to_str()
+            .and_then(|f| f.parse::<u32>().ok())
+        {
+            Some(fd) => fd,
+            None => continue,
+        };
+        // The file descriptor might have been closed in the meantime.
+        if let Some(fd) = get_file_descriptor(pid, fd) {
+            fds.push(fd);
+        }
+    }
+    fds.sort_by_key(|fd| fd.fd);
+    Ok(fds)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_file_descriptors(_pid: Pid) -> io::Result<Vec<FileDescriptor>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+/// Returns a readable version of the flags of a file descriptor.
+#[cfg(target_os = "linux")]
+pub fn format_open_flags(flags: i32) -> String {
+    const FLAGS: &[(i32, &str)] = &[
+        (libc::O_APPEND, "O_APPEND"),
+        (libc::O_CREAT, "O_CREAT"),
+        (libc::O_NONBLOCK, "O_NONBLOCK"),
+        (libc::O_SYNC, "O_SYNC"),
+        (libc::O_DSYNC, "O_DSYNC"),
+        (libc::O_DIRECT, "O_DIRECT"),
+        (libc::O_DIRECTORY, "O_DIRECTORY"),
+        (libc::O_NOFOLLOW, "O_NOFOLLOW"),
+        (libc::O_NOATIME, "O_NOATIME"),
+        (libc::O_CLOEXEC, "O_CLOEXEC"),
+        (libc::O_PATH, "O_PATH"),
+    ];
+
+    let mut names = vec![match flags & libc::O_ACCMODE {
+        libc::O_WRONLY => "O_WRONLY",
+        libc::O_RDWR => "O_RDWR",
+        _ => "O_RDONLY",
+    }];
+    names.extend(
+        FLAGS
+            .iter()
+            .filter(|(flag, _)| flags & flag == *flag)
+            .map(|(_, name)| *name),
+    );
+    names.join(" | ")
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn format_open_flags(flags: i32) -> String {
+    format!("{:o}", flags)
+}
+
+pub struct ResourceLimit {
+    pub name: String,
+    /// `None` means "unlimited".
+    pub soft: Option<u64>,
+    /// `None` means "unlimited".
+    pub hard: Option<u64>,
+    /// Empty if the limit has no unit (a number of processes for example).
+    pub unit: String,
+}
+
+/// Returns the resource limits of the process (the ones displayed by `ulimit`).
+#[cfg(target_os = "linux")]
+pub fn get_limits(pid: Pid) -> io::Result<Vec<ResourceLimit>> {
+    parse_limits(&fs::read_to_string(format!("/proc/{}/limits", pid))?)
+}
+
+/// Parses the content of `/proc/<pid>/limits`.
+#[cfg(target_os = "linux")]
+fn parse_limits(content: &str) -> io::Result<Vec<ResourceLimit>> {
+    let mut lines = content.lines();
+    let header = lines.next().unwrap_or("");
+    // The columns are aligned and the names contain spaces, so we rely on the header to split
+    // the lines.
+    let (soft_pos, hard_pos, unit_pos) = match (
+        header.find("Soft Limit"),
+        header.find("Hard Limit"),
+        header.find("Units"),
+    ) {
+        (Some(soft), Some(hard), Some(unit)) => (soft, hard, unit),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown limits format",
+            ))
+        }
+    };
+    let parse = |value: &str| value.trim().parse::<u64>().ok();
+
+    Ok(lines
+        .filter(|line| line.len() > hard_pos)
+        .map(|line| ResourceLimit {
+            name: line[..soft_pos].trim().to_owned(),
+            soft: parse(&line[soft_pos..hard_pos]),
+            hard: parse(&line[hard_pos..unit_pos.min(line.len())]),
+            unit: line.get(unit_pos..).unwrap_or("").trim().to_owned(),
+        })
+        .collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_limits(_pid: Pid) -> io::Result<Vec<ResourceLimit>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+/// Returns the environment variables of the process (in the `NAME=value` form).
+#[cfg(target_os = "linux")]
+pub fn get_environment(pid: Pid) -> io::Result<Vec<String>> {
+    let content = fs::read(format!("/proc/{}/environ", pid))?;
+
+    Ok(content
+        .split(|c| *c == 0)
+        .filter(|var| !var.is_empty())
+        .map(|var| String::from_utf8_lossy(var).into_owned())
+        .collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_environment(_pid: Pid) -> io::Result<Vec<String>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+/// A line of `/proc/<pid>/cgroup`.
+pub struct Cgroup {
+    pub hierarchy: u32,
+    /// The comma-separated list of controllers bound to the hierarchy. It's empty for the cgroup
+    /// v2 hierarchy.
+    pub controllers: String,
+    pub path: String,
+}
+
+impl Cgroup {
+    pub fn is_v2(&self) -> bool {
+        self.hierarchy == 0 && self.controllers.is_empty()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn get_directory(&self) -> String {
+        if self.is_v2() {
+            format!("{}{}", CGROUP_ROOT, self.path)
+        } else {
+            // Named hierarchies (like "name=systemd") are mounted under their name.
+            let name = self.controllers.trim_start_matches("name=");
+            format!("{}/{}{}", CGROUP_ROOT, name, self.path)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn has_controller(&self, controller: &str) -> bool {
+        self.is_v2() || self.controllers.split(',').any(|c| c == controller)
+    }
+}
+
+#[cfg(target_os = "linux")]
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Returns the control groups of the process, for both the v1 and v2 hierarchies.
+#[cfg(target_os = "linux")]
+pub fn get_cgroups(pid: Pid) -> io::Result<Vec<Cgroup>> {
+    Ok(parse_cgroups(&fs::read_to_string(format!(
+        "/proc/{}/cgroup",
+        pid
+    ))?))
+}
+
+/// Parses the content of `/proc/<pid>/cgroup` (`hierarchy:controllers:path` lines).
+#[cfg(target_os = "linux")]
+fn parse_cgroups(content: &str) -> Vec<Cgroup> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ':');
+            Some(Cgroup {
+                hierarchy: parts.next()?.parse().ok()?,
+                controllers: parts.next()?.to_owned(),
+                path: parts.next()?.to_owned(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_cgroups(_pid: Pid) -> io::Result<Vec<Cgroup>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+/// Returns the systemd unit of the process (the last ".service" or ".scope" in its cgroup path)
+/// or, if there is none, its cgroup path. The v2 hierarchy is preferred over the v1 ones.
+pub fn get_cgroup_unit(pid: Pid) -> Option<String> {
+    let cgroups = get_cgroups(pid).ok()?;
+    let cgroup = cgroups
+        .iter()
+        .find(|c| c.is_v2())
+        .or_else(|| cgroups.iter().find(|c| c.controllers == "name=systemd"))
+        .or_else(|| cgroups.first())?;
+
+    Some(
+        cgroup
+            .path
+            .rsplit('/')
+            .find(|part| part.ends_with(".service") || part.ends_with(".scope"))
+            .unwrap_or(&cgroup.path)
+            .to_owned(),
+    )
+}
+
+/// Reads the first file that can be read among the given ones inside the cgroup directories
+/// which have the given controller.
+#[cfg(target_os = "linux")]
+fn read_cgroup_file(
+    cgroups: &[Cgroup],
+    controller: &str,
+    v1: &str,
+    v2: &str,
+) -> io::Result<String> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no such cgroup controller");
+
+    for cgroup in cgroups.iter().filter(|c| c.has_controller(controller)) {
+        let file = if cgroup.is_v2() { v2 } else { v1 };
+        match fs::read_to_string(format!("{}/{}", cgroup.get_directory(), file)) {
+            Ok(content) => return Ok(content.trim().to_owned()),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+/// Returns the memory limit (in bytes) of the cgroup. `None` means "unlimited".
+#[cfg(target_os = "linux")]
+pub fn get_cgroup_memory_limit(cgroups: &[Cgroup]) -> io::Result<Option<u64>> {
+    parse_memory_limit(&read_cgroup_file(
+        cgroups,
+        "memory",
+        "memory.limit_in_bytes",
+        "memory.max",
+    )?)
+}
+
+/// Parses the content of `memory.max` (v2) or `memory.limit_in_bytes` (v1).
+#[cfg(target_os = "linux")]
+fn parse_memory_limit(content: &str) -> io::Result<Option<u64>> {
+    match content.parse::<u64>() {
+        // cgroup v1 uses a huge value (rounded to the page size) to mean "unlimited".
+        Ok(limit) if limit >= i64::MAX as u64 & !0xfff => Ok(None),
+        Ok(limit) => Ok(Some(limit)),
+        Err(_) if content == "max" => Ok(None),
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid memory limit",
+        )),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_cgroup_memory_limit(_cgroups: &[Cgroup]) -> io::Result<Option<u64>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+/// Returns the number of CPUs the cgroup can use (its quota divided by its period). `None`
+/// means "unlimited".
+#[cfg(target_os = "linux")]
+pub fn get_cgroup_cpu_limit(cgroups: &[Cgroup]) -> io::Result<Option<f64>> {
+    let content = read_cgroup_file(cgroups, "cpu", "cpu.cfs_quota_us", "cpu.max")?;
+
+    parse_cpu_limit(&content, || {
+        read_cgroup_file(cgroups, "cpu", "cpu.cfs_period_us", "cpu.max")
+    })
+}
+
+/// Parses the content of `cpu.max` (v2) or `cpu.cfs_quota_us` (v1). `read_period` is only
+/// called for v1, where the period is in another file.
+#[cfg(target_os = "linux")]
+fn parse_cpu_limit<F: FnOnce() -> io::Result<String>>(
+    content: &str,
+    read_period: F,
+) -> io::Result<Option<f64>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid CPU limit");
+    let mut parts = content.split_whitespace();
+    let quota = parts.next().ok_or_else(invalid)?;
+
+    if quota == "max" || quota == "-1" {
+        return Ok(None);
+    }
+    let quota = quota.parse::<u64>().map_err(|_| invalid())?;
+    // With cgroup v2, the period is on the same line. With v1, it's in another file.
+    let period = match parts.next() {
+        Some(period) => period.to_owned(),
+        None => read_period()?,
+    };
+    match period.parse::<u64>() {
+        Ok(period) if period > 0 => Ok(Some(quota as f64 / period as f64)),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_cgroup_cpu_limit(_cgroups: &[Cgroup]) -> io::Result<Option<f64>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+/// Writes the limit in the file, enabling the controller for the children of the parent cgroup
+/// first if needed.
+#[cfg(target_os = "linux")]
+fn write_cgroup_limit(
+    directory: &Path,
+    controller: &str,
+    file: &str,
+    value: &str,
+) -> io::Result<()> {
+    let path = directory.join(file);
+
+    if !path.exists() {
+        if let Some(parent) = directory.parent() {
+            // It fails if we're not allowed to, in which case the error below is more helpful.
+            let _ = fs::write(
+                parent.join("cgroup.subtree_control"),
+                format!("+{}", controller),
+            );
+        }
+        if !path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("the {} controller isn't available", controller),
+            ));
+        }
+    }
+    fs::write(path, value)
+}
+
+/// Creates a new cgroup (v2 only) with the given limits and returns its directory. The memory
+/// limit is in bytes and the CPU one in number of CPUs.
+#[cfg(target_os = "linux")]
+pub fn create_cgroup(
+    name: &str,
+    memory_max: Option<u64>,
+    cpu_max: Option<f64>,
+) -> io::Result<PathBuf> {
+    const CPU_PERIOD: u64 = 100_000;
+
+    let cgroups = get_cgroups(::std::process::id() as Pid)?;
+    let own = cgroups.iter().find(|c| c.is_v2()).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "the cgroup v2 hierarchy isn't mounted",
+        )
+    })?;
+    // A cgroup containing processes cannot have children using controllers, so the new cgroup is
+    // created next to ours.
+    let own_directory = PathBuf::from(own.get_directory());
+    let parent = match own_directory.parent() {
+        Some(parent) if own.path != "/" => parent.to_path_buf(),
+        _ => PathBuf::from(CGROUP_ROOT),
+    };
+    let directory = parent.join(name);
+
+    fs::create_dir(&directory)?;
+    let mut result = Ok(());
+    if let Some(memory_max) = memory_max {
+        result = write_cgroup_limit(&directory, "memory", "memory.max", &memory_max.to_string());
+    }
+    if let (Ok(()), Some(cpu_max)) = (&result, cpu_max) {
+        let quota = (cpu_max * CPU_PERIOD as f64) as u64;
+        result = write_cgroup_limit(
+            &directory,
+            "cpu",
+            "cpu.max",
+            &format!("{} {}", quota, CPU_PERIOD),
+        );
+    }
+    match result {
+        Ok(()) => Ok(directory),
+        Err(e) => {
+            let _ = fs::remove_dir(&directory);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn create_cgroup(
+    _name: &str,
+    _memory_max: Option<u64>,
+    _cpu_max: Option<f64>,
+) -> io::Result<PathBuf> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+pub struct Namespace {
+    /// The namespace type, like "net" or "pid".
+    pub name: String,
+    pub inode: u64,
+}
+
+/// Returns the namespaces of the process. Namespaces which cannot be read (because of missing
+/// permissions for example) are skipped.
+#[cfg(target_os = "linux")]
+pub fn get_namespaces(pid: Pid) -> io::Result<Vec<Namespace>> {
+    let mut namespaces = fs::read_dir(format!("/proc/{}/ns", pid))?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let target = fs::read_link(entry.path()).ok()?;
+            // The target looks like "net:[4026531992]".
+            let target = target.to_str()?;
+            let start = target.find(":[")?;
+            Some(Namespace {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                inode: target[start + 2..].trim_end_matches(']').parse().ok()?,
+            })
+        })
+        .collect::<Vec<_>>();
+    namespaces.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(namespaces)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_namespaces(_pid: Pid) -> io::Result<Vec<Namespace>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+/// A memory mapping of the process. All the sizes are in bytes.
+pub struct MemoryMapping {
+    pub start: u64,
+    pub end: u64,
+    pub permissions: String,
+    /// The mapped file or a pseudo path such as "[heap]" or "[stack]". Empty for anonymous
+    /// mappings.
+    pub path: String,
+    pub rss: u64,
+    pub pss: u64,
+    pub swap: u64,
+    pub shared_dirty: u64,
+    pub private_dirty: u64,
+}
+
+#[cfg(target_os = "linux")]
+fn parse_mapping_header(line: &str) -> Option<MemoryMapping> {
+    let mut parts = line.split_whitespace();
+    let mut range = parts.next()?.splitn(2, '-');
+    let start = u64::from_str_radix(range.next()?, 16).ok()?;
+    let end = u64::from_str_radix(range.next()?, 16).ok()?;
+    let permissions = parts.next()?.to_owned();
+    // We skip the offset, the device and the inode.
+    let path = parts.skip(3).collect::<Vec<_>>().join(" ");
+
+    Some(MemoryMapping {
+        start,
+        end,
+        permissions,
+        path,
+        rss: 0,
+        pss: 0,
+        swap: 0,
+        shared_dirty: 0,
+        private_dirty: 0,
+    })
+}
+
+/// Returns the memory mappings of the process from its `smaps` file.
+#[cfg(target_os = "linux")]
+pub fn get_memory_mappings(pid: Pid) -> io::Result<Vec<MemoryMapping>> {
+    let content = fs::read_to_string(format!("/proc/{}/smaps", pid))?;
+    let mut mappings: Vec<MemoryMapping> = Vec::new();
+
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let key = match parts.next() {
+            Some(key) => key,
+            None => continue,
+        };
+        if !key.ends_with(':') {
+            // It's the beginning of a new mapping.
+            if let Some(mapping) = parse_mapping_header(line) {
+                mappings.push(mapping);
+            }
+            continue;
+        }
+        let mapping = match mappings.last_mut() {
+            Some(mapping) => mapping,
+            None => continue,
+        };
+        // The values are in kB.
+        let value = match parts.next().and_then(|v| v.parse::<u64>().ok()) {
+            Some(value) => value * 1_024,
+            None => continue,
+        };
+        match key {
+            "Rss:" => mapping.rss = value,
+            "Pss:" => mapping.pss = value,
+            "Swap:" => mapping.swap = value,
+            "Shared_Dirty:" => mapping.shared_dirty = value,
+            "Private_Dirty:" => mapping.private_dirty = value,
+            _ => {}
+        }
+    }
+    Ok(mappings)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_memory_mappings(_pid: Pid) -> io::Result<Vec<MemoryMapping>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+/// A socket opened by the process.
+pub struct Connection {
+    pub inode: u64,
+    /// "tcp", "tcp6", "udp", "udp6" or "unix".
+    pub protocol: &'static str,
+    pub local_address: String,
+    pub remote_address: String,
+    pub state: String,
+    pub send_queue: Option<u64>,
+    pub receive_queue: Option<u64>,
+}
+
+/// Returns the inodes of the sockets opened by the process.
+#[cfg(target_os = "linux")]
+fn get_socket_inodes(pid: Pid) -> io::Result<HashSet<u64>> {
+    Ok(get_file_descriptors(pid)?
+        .into_iter()
+        .filter_map(|fd| {
+            if fd.target.starts_with("socket:[") && fd.target.ends_with(']') {
+                fd.target[8..fd.target.len() - 1].parse().ok()
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+#[cfg(target_os = "linux")]
+fn get_tcp_state_name(state: u8) -> &'static str {
+    match state {
+        0x01 => "ESTABLISHED",
+        0x02 => "SYN_SENT",
+        0x03 => "SYN_RECV",
+        0x04 => "FIN_WAIT1",
+        0x05 => "FIN_WAIT2",
+        0x06 => "TIME_WAIT",
+        0x07 => "CLOSE",
+        0x08 => "CLOSE_WAIT",
+        0x09 => "LAST_ACK",
+        0x0A => "LISTEN",
+        0x0B => "CLOSING",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Parses addresses like `0100007F:0035`. The address is written as the hexadecimal value of
+/// 32 bits words in the host byte order.
+#[cfg(target_os = "linux")]
+fn parse_socket_address(address: &str) -> Option<SocketAddr> {
+    let mut parts = address.splitn(2, ':');
+    let ip = parts.next()?;
+    let port = u16::from_str_radix(parts.next()?, 16).ok()?;
+    let mut bytes = Vec::with_capacity(16);
+
+    for pos in (0..ip.len()).step_by(8) {
+        let word = u32::from_str_radix(ip.get(pos..pos + 8)?, 16).ok()?;
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+    let ip = match bytes.len() {
+        4 => IpAddr::from([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        16 => {
+            let mut ip = [0; 16];
+            ip.copy_from_slice(&bytes);
+            IpAddr::from(ip)
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+/// Parses a line of `/proc/<pid>/net/tcp`, `udp`, `tcp6` or `udp6`.
+#[cfg(target_os = "linux")]
+fn parse_inet_connection(line: &str, protocol: &'static str) -> Option<Connection> {
+    let fields = line.split_whitespace().collect::<Vec<_>>();
+    if fields.len() < 10 {
+        return None;
+    }
+    let inode = fields[9].parse::<u64>().ok()?;
+    let state = u8::from_str_radix(fields[3], 16).unwrap_or(0);
+    let mut queues = fields[4]
+        .splitn(2, ':')
+        .map(|q| u64::from_str_radix(q, 16).ok());
+    let format_address = |address| {
+        parse_socket_address(address)
+            .map(|a| a.to_string())
+            .unwrap_or_else(|| address.to_owned())
+    };
+
+    Some(Connection {
+        inode,
+        protocol,
+        local_address: format_address(fields[1]),
+        remote_address: format_address(fields[2]),
+        state: if protocol.starts_with("udp") {
+            // UDP sockets use the TCP states but only two of them make sense.
+            if state == 0x01 {
+                "ESTABLISHED"
+            } else {
+                "UNCONNECTED"
+            }
+            .to_owned()
+        } else {
+            get_tcp_state_name(state).to_owned()
+        },
+        send_queue: queues.next().unwrap_or(None),
+        receive_queue: queues.next().unwrap_or(None),
+    })
+}
+
+/// Parses a line of `/proc/<pid>/net/unix`.
+#[cfg(target_os = "linux")]
+fn parse_unix_connection(line: &str) -> Option<Connection> {
+    let fields = line.split_whitespace().collect::<Vec<_>>();
+    if fields.len() < 7 {
+        return None;
+    }
+    let inode = fields[6].parse::<u64>().ok()?;
+    let kind = match fields[4] {
+        "0001" => "stream",
+        "0002" => "dgram",
+        "0005" => "seqpacket",
+        _ => "",
+    };
+    let state = match fields[5] {
+        "01" => "UNCONNECTED",
+        "02" => "CONNECTING",
+        "03" => "CONNECTED",
+        "04" => "DISCONNECTING",
+        _ => "UNKNOWN",
+    };
+
+    Some(Connection {
+        inode,
+        protocol: "unix",
+        local_address: fields.get(7).cloned().unwrap_or("").to_owned(),
+        remote_address: String::new(),
+        state: if kind.is_empty() {
+            state.to_owned()
+        } else {
+            format!("{} ({})", state, kind)
+        },
+        send_queue: None,
+        receive_queue: None,
+    })
+}
+
+/// Reads the sockets from one of the files of `/proc/<pid>/net` and keeps the ones whose inode
+/// is in `inodes`.
+#[cfg(target_os = "linux")]
+fn get_protocol_connections<F: Fn(&str) -> Option<Connection>>(
+    pid: Pid,
+    file: &str,
+    inodes: &HashSet<u64>,
+    parse: F,
+    connections: &mut Vec<Connection>,
+) {
+    let content = match fs::read_to_string(format!("/proc/{}/net/{}", pid, file)) {
+        Ok(content) => content,
+        // The protocol might not be supported (IPv6 could be disabled for example).
+        Err(_) => return,
+    };
+    connections.extend(
+        content
+            .lines()
+            .skip(1)
+            .filter_map(parse)
+            .filter(|connection| inodes.contains(&connection.inode)),
+    );
+}
+
+/// Lists the sockets opened by the process. The files in `/proc/<pid>/net` are used instead of
+/// the ones in `/proc/net` so the sockets are found even if the process is in another network
+/// namespace.
+#[cfg(target_os = "linux")]
+pub fn get_connections(pid: Pid) -> io::Result<Vec<Connection>> {
+    let inodes = get_socket_inodes(pid)?;
+    let mut connections = Vec::new();
+
+    if inodes.is_empty() {
+        return Ok(connections);
+    }
+    for protocol in &["tcp", "tcp6", "udp", "udp6"] {
+        get_protocol_connections(
+            pid,
+            protocol,
+            &inodes,
+            |line| parse_inet_connection(line, protocol),
+            &mut connections,
+        );
+    }
+    get_protocol_connections(
+        pid,
+        "unix",
+        &inodes,
+        parse_unix_connection,
+        &mut connections,
+    );
+    Ok(connections)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_connections(_pid: Pid) -> io::Result<Vec<Connection>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_descriptor_kind() {
+        assert_eq!(get_file_descriptor_kind("socket:[22170]"), "socket");
+        assert_eq!(get_file_descriptor_kind("pipe:[30561]"), "pipe");
+        assert_eq!(get_file_descriptor_kind("anon_inode:[eventfd]"), "eventfd");
+        assert_eq!(
+            get_file_descriptor_kind("anon_inode:[eventpoll]"),
+            "anon inode"
+        );
+        assert_eq!(get_file_descriptor_kind("/dev/null"), "device");
+        assert_eq!(get_file_descriptor_kind("/home/user/.bash_history"), "file");
+        assert_eq!(get_file_descriptor_kind("net:[4026531840]"), "other");
+    }
+
+    #[test]
+    fn fdinfo() {
+        let content = "pos:\t1024\nflags:\t02100002\nmnt_id:\t25\nino:\t1318\n";
+        assert_eq!(parse_fdinfo(content), (Some(0o2100002), Some(1024)));
+
+        // The epoll descriptors list the watched file descriptors after the common fields.
+        let content = "pos:\t0\nflags:\t02\nmnt_id:\t15\nino:\t1057\n\
+                       tfd:        5 events:       19 data:                5  pos:0 ino:2fb6 sdev:8\n";
+        assert_eq!(parse_fdinfo(content), (Some(0o2), Some(0)));
+
+        assert_eq!(parse_fdinfo(""), (None, None));
+        assert_eq!(parse_fdinfo("pos:\tx\nflags:\t9\n"), (None, None));
+    }
+
+    #[test]
+    fn open_flags() {
+        assert_eq!(format_open_flags(0), "O_RDONLY");
+        assert_eq!(
+            format_open_flags(libc::O_RDWR | libc::O_CLOEXEC),
+            "O_RDWR | O_CLOEXEC"
+        );
+        assert_eq!(
+            format_open_flags(libc::O_WRONLY | libc::O_APPEND | libc::O_CREAT | libc::O_NONBLOCK),
+            "O_WRONLY | O_APPEND | O_CREAT | O_NONBLOCK"
+        );
+        assert_eq!(
+            format_open_flags(libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW),
+            "O_RDONLY | O_DIRECTORY | O_NOFOLLOW"
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let content = "\
+Limit                     Soft Limit           Hard Limit           Units     
+Max cpu time              unlimited            unlimited            seconds   
+Max file size             unlimited            unlimited            bytes     
+Max processes             127154               127154               processes 
+Max open files            1024                 524288               files     
+Max locked memory         8388608              8388608              bytes     
+Max pending signals       127154               127154               signals   
+Max nice priority         0                    0                    
+Max realtime timeout      unlimited            unlimited            us        
+";
+        let limits = parse_limits(content).unwrap();
+        assert_eq!(limits.len(), 8);
+
+        let check = |index: usize, name, soft, hard, unit| {
+            let limit: &ResourceLimit = &limits[index];
+            assert_eq!(
+                (
+                    limit.name.as_str(),
+                    limit.soft,
+                    limit.hard,
+                    limit.unit.as_str()
+                ),
+                (name, soft, hard, unit)
+            );
+        };
+        check(0, "Max cpu time", None, None, "seconds");
+        check(
+            2,
+            "Max processes",
+            Some(127_154),
+            Some(127_154),
+            "processes",
+        );
+        check(3, "Max open files", Some(1024), Some(524_288), "files");
+        check(
+            4,
+            "Max locked memory",
+            Some(8_388_608),
+            Some(8_388_608),
+            "bytes",
+        );
+        check(6, "Max nice priority", Some(0), Some(0), "");
+
+        assert!(parse_limits("").is_err());
+        assert!(parse_limits("Limit    Soft    Hard\n").is_err());
+    }
+
+    #[test]
+    fn cgroups() {
+        let cgroups = parse_cgroups(
+            "12:cpu,cpuacct:/user.slice\n\
+             1:name=systemd:/user.slice/user-1000.slice/session-2.scope\n\
+             0::/user.slice/user-1000.slice/session-2.scope\n",
+        );
+        assert_eq!(cgroups.len(), 3);
+        assert_eq!(cgroups[0].hierarchy, 12);
+        assert_eq!(cgroups[0].controllers, "cpu,cpuacct");
+        assert_eq!(cgroups[0].path, "/user.slice");
+        assert!(!cgroups[0].is_v2());
+        assert!(cgroups[0].has_controller("cpu"));
+        assert!(!cgroups[0].has_controller("cpuset"));
+        assert_eq!(
+            cgroups[0].get_directory(),
+            "/sys/fs/cgroup/cpu,cpuacct/user.slice"
+        );
+        assert_eq!(
+            cgroups[1].get_directory(),
+            "/sys/fs/cgroup/systemd/user.slice/user-1000.slice/session-2.scope"
+        );
+        assert!(cgroups[2].is_v2());
+        assert!(cgroups[2].has_controller("memory"));
+        assert_eq!(
+            cgroups[2].get_directory(),
+            "/sys/fs/cgroup/user.slice/user-1000.slice/session-2.scope"
+        );
+
+        // The path can contain colons.
+        let cgroups = parse_cgroups("0::/system.slice/a:b.service\ninvalid\n");
+        assert_eq!(cgroups.len(), 1);
+        assert_eq!(cgroups[0].path, "/system.slice/a:b.service");
+    }
+
+    #[test]
+    fn memory_limit() {
+        assert_eq!(parse_memory_limit("max").unwrap(), None);
+        assert_eq!(parse_memory_limit("536870912").unwrap(), Some(536_870_912));
+        // The "unlimited" value of cgroup v1.
+        assert_eq!(parse_memory_limit("9223372036854771712").unwrap(), None);
+        assert!(parse_memory_limit("").is_err());
+        assert!(parse_memory_limit("512M").is_err());
+    }
+
+    #[test]
+    fn cpu_limit() {
+        let no_period = || -> io::Result<String> { panic!("the period shouldn't be read") };
+        let period = || Ok("100000".to_owned());
+
+        assert_eq!(parse_cpu_limit("max 100000", no_period).unwrap(), None);
+        assert_eq!(
+            parse_cpu_limit("50000 100000", no_period).unwrap(),
+            Some(0.5)
+        );
+        assert_eq!(parse_cpu_limit("-1", no_period).unwrap(), None);
+        assert_eq!(parse_cpu_limit("200000", period).unwrap(), Some(2.0));
+        assert!(parse_cpu_limit("", no_period).is_err());
+        assert!(parse_cpu_limit("half 100000", no_period).is_err());
+        assert!(parse_cpu_limit("50000 0", no_period).is_err());
+        assert!(
+            parse_cpu_limit("50000", || Err(io::Error::from(io::ErrorKind::NotFound))).is_err()
+        );
+    }
+
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn socket_address() {
+        assert_eq!(
+            parse_socket_address("0100007F:0035"),
+            Some("127.0.0.1:53".parse().unwrap())
+        );
+        assert_eq!(
+            parse_socket_address("00000000000000000000000001000000:0277"),
+            Some("[::1]:631".parse().unwrap())
+        );
+        assert_eq!(
+            parse_socket_address("B80D0120000000000000000001000000:01BB"),
+            Some("[2001:db8::1]:443".parse().unwrap())
+        );
+        assert_eq!(parse_socket_address("0100007F"), None);
+        assert_eq!(parse_socket_address("0100007:0035"), None);
+        assert_eq!(parse_socket_address("0100007G:0035"), None);
+    }
+
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn inet_connection() {
+        let line = "   1: 0F02000A:D4C2 2E6BD9AC:01BB 01 00000010:00000020 02:000004E8 00000000  \
+                    1000        0 123456 2 0000000000000000 20 4 30 10 -1";
+        let connection = parse_inet_connection(line, "tcp").unwrap();
+        assert_eq!(connection.inode, 123456);
+        assert_eq!(connection.protocol, "tcp");
+        assert_eq!(connection.local_address, "10.0.2.15:54466");
+        assert_eq!(connection.remote_address, "172.217.107.46:443");
+        assert_eq!(connection.state, "ESTABLISHED");
+        assert_eq!(connection.send_queue, Some(16));
+        assert_eq!(connection.receive_queue, Some(32));
+
+        let line = "   0: 00000000000000000000000001000000:0277 \
+                    00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 \
+                    00000000     0        0 16870 1 0000000000000000 100 0 0 10 0";
+        let connection = parse_inet_connection(line, "tcp6").unwrap();
+        assert_eq!(connection.local_address, "[::1]:631");
+        assert_eq!(connection.remote_address, "[::]:0");
+        assert_eq!(connection.state, "LISTEN");
+
+        // UDP sockets only have two states.
+        let line = "  5: 00000000:0044 00000000:0000 07 00000000:00000000 00:00000000 00000000  \
+                   0        0 20512 2 0000000000000000 0";
+        let connection = parse_inet_connection(line, "udp").unwrap();
+        assert_eq!(connection.local_address, "0.0.0.0:68");
+        assert_eq!(connection.state, "UNCONNECTED");
+        assert_eq!(connection.inode, 20512);
+    }
+
+    #[test]
+    fn inet_connection_header() {
+        let header =
+            "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   \
+                      uid  timeout inode";
+        assert!(parse_inet_connection(header, "tcp").is_none());
+        assert!(parse_inet_connection("", "tcp").is_none());
+    }
+
+    #[test]
+    fn unix_connection() {
+        let line = "0000000000000000: 00000002 00000000 00010000 0001 01 22170 /run/systemd/notify";
+        let connection = parse_unix_connection(line).unwrap();
+        assert_eq!(connection.inode, 22170);
+        assert_eq!(connection.protocol, "unix");
+        assert_eq!(connection.local_address, "/run/systemd/notify");
+        assert_eq!(connection.state, "UNCONNECTED (stream)");
+
+        let line = "0000000000000000: 00000003 00000000 00000000 0002 03 30561";
+        let connection = parse_unix_connection(line).unwrap();
+        assert_eq!(connection.local_address, "");
+        assert_eq!(connection.state, "CONNECTED (dgram)");
+
+        let header = "Num       RefCount Protocol Flags    Type St Inode Path";
+        assert!(parse_unix_connection(header).is_none());
+    }
+}
@@ -0,0 +1,712 @@
+//! A small query language used to filter the process list.
+//!
+//! A query is a list of conditions such as `cpu>20 mem>500M user=postgres name~"^java"`.
+//! Conditions next to each other are combined with `AND`, and `OR`, `NOT` and parentheses can be
+//! used as well (`&&`, `||` and `!` work too). A word which isn't a condition matches the PID or
+//! the name of the process (and its command line and executable path if
+//! `QueryOptions::command_line` is set) if one contains the other.
+
+use regex::{Regex, RegexBuilder};
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Pid,
+    Name,
+    Cpu,
+    Memory,
+    /// The sum of the disk read and write rates.
+    Disk,
+    DiskRead,
+    DiskWrite,
+    User,
+    State,
+    Threads,
+    Nice,
+    VirtualMemory,
+    ParentPid,
+    Command,
+    Exe,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        Some(match name.to_lowercase().as_str() {
+            "pid" => Field::Pid,
+            "name" => Field::Name,
+            "cpu" => Field::Cpu,
+            "mem" | "memory" => Field::Memory,
+            "disk" | "io" => Field::Disk,
+            "read" | "disk_read" => Field::DiskRead,
+            "write" | "disk_write" => Field::DiskWrite,
+            "user" => Field::User,
+            "state" | "status" => Field::State,
+            "threads" => Field::Threads,
+            "nice" => Field::Nice,
+            "vmem" | "virtual_memory" => Field::VirtualMemory,
+            "ppid" | "parent" => Field::ParentPid,
+            "cmd" | "command" => Field::Command,
+            "exe" => Field::Exe,
+            _ => return None,
+        })
+    }
+
+    pub fn is_numeric(self) -> bool {
+        !self.is_text()
+    }
+
+    fn is_text(self) -> bool {
+        matches!(
+            self,
+            Field::Name | Field::User | Field::State | Field::Command | Field::Exe
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Lower,
+    LowerOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Match,
+}
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Condition(Field, Operator, Value),
+    /// A word on its own, matched against the PID and the name (it's either a `Value::Text` or a
+    /// `Value::Regex`). Like in the old filter, a text also matches if it contains the PID or the
+    /// name, so "firefox-esr" matches "firefox".
+    Text(Value),
+}
+
+/// Changes how the text is matched. They are set from the buttons next to the filter entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QueryOptions {
+    /// Words on their own are regular expressions.
+    pub regex: bool,
+    pub case_sensitive: bool,
+    /// Words on their own are matched against the command line and the executable path too.
+    pub command_line: bool,
+}
+
+impl QueryOptions {
+    fn normalize(self, text: &str) -> String {
+        if self.case_sensitive {
+            text.to_owned()
+        } else {
+            text.to_lowercase()
+        }
+    }
+
+    fn build_regex(self, pattern: &str, position: usize) -> Result<Regex, ParseError> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map_err(|e| {
+                // The regex errors are displayed over several lines, we only keep the
+                // description.
+                let e = e.to_string();
+                ParseError {
+                    message: format!("Invalid regex: {}", e.lines().last().unwrap_or("")),
+                    position,
+                }
+            })
+    }
+}
+
+/// Gives access to the values of a process when evaluating a query.
+pub trait Row {
+    fn get_number(&self, field: Field) -> Option<f64>;
+    fn get_text(&self, field: Field) -> String;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    /// Position (in characters) of the error in the query.
+    pub position: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (at position {})", self.message, self.position + 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    OpenParen,
+    CloseParen,
+    And,
+    Or,
+    Not,
+    Operator(Operator),
+    Word(String),
+    Quoted(String),
+}
+
+fn is_special(c: char) -> bool {
+    c.is_whitespace() || "()<>=!~\"&|".contains(c)
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let start = pos;
+        let next = chars.get(pos + 1).cloned();
+        let token = match chars[pos] {
+            c if c.is_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            '&' if next == Some('&') => {
+                pos += 1;
+                Token::And
+            }
+            '|' if next == Some('|') => {
+                pos += 1;
+                Token::Or
+            }
+            '!' if next == Some('=') => {
+                pos += 1;
+                Token::Operator(Operator::NotEqual)
+            }
+            '!' => Token::Not,
+            '<' if next == Some('=') => {
+                pos += 1;
+                Token::Operator(Operator::LowerOrEqual)
+            }
+            '>' if next == Some('=') => {
+                pos += 1;
+                Token::Operator(Operator::GreaterOrEqual)
+            }
+            '<' => Token::Operator(Operator::Lower),
+            '>' => Token::Operator(Operator::Greater),
+            '=' if next == Some('=') => {
+                pos += 1;
+                Token::Operator(Operator::Equal)
+            }
+            '=' => Token::Operator(Operator::Equal),
+            '~' => Token::Operator(Operator::Match),
+            '"' => {
+                let mut text = String::new();
+                pos += 1;
+                loop {
+                    match chars.get(pos) {
+                        None => {
+                            return Err(ParseError {
+                                message: "Unclosed quote".to_owned(),
+                                position: start,
+                            })
+                        }
+                        Some('"') => break,
+                        Some('\\') if chars.get(pos + 1) == Some(&'"') => {
+                            text.push('"');
+                            pos += 1;
+                        }
+                        Some(c) => text.push(*c),
+                    }
+                    pos += 1;
+                }
+                Token::Quoted(text)
+            }
+            c if is_special(c) => {
+                return Err(ParseError {
+                    message: format!("Unexpected character '{}'", c),
+                    position: start,
+                })
+            }
+            _ => {
+                while pos + 1 < chars.len() && !is_special(chars[pos + 1]) {
+                    pos += 1;
+                }
+                let word = chars[start..=pos].iter().collect::<String>();
+                match word.to_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word),
+                }
+            }
+        };
+        tokens.push((token, start));
+        pos += 1;
+    }
+    Ok(tokens)
+}
+
+/// Parses numbers like `20`, `1.5`, `20%` or `500M` (units are powers of 1000, like the ones
+/// displayed in the process list).
+pub fn parse_number(s: &str) -> Option<f64> {
+    let lower = s.to_lowercase();
+    let mut s = lower.trim_end_matches('%');
+    if s.ends_with('b') {
+        s = &s[..s.len() - 1];
+    }
+    let (s, multiplier) = match s.chars().last() {
+        Some('k') => (&s[..s.len() - 1], 1_000.),
+        Some('m') => (&s[..s.len() - 1], 1_000_000.),
+        Some('g') => (&s[..s.len() - 1], 1_000_000_000.),
+        Some('t') => (&s[..s.len() - 1], 1_000_000_000_000.),
+        _ => (s, 1.),
+    };
+    s.parse::<f64>().ok().map(|n| n * multiplier)
+}
+
+struct Parser {
+    options: QueryOptions,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|(_, p)| *p)
+            .unwrap_or(self.len)
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, ParseError> {
+        Err(ParseError {
+            message: message.to_owned(),
+            position: self.position(),
+        })
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(t, _)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                None | Some(Token::Or) | Some(Token::CloseParen) => return Ok(expr),
+                Some(Token::And) => {
+                    self.next();
+                }
+                _ => {}
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let start = self.position();
+        match self.next() {
+            Some(Token::OpenParen) => {
+                let expr = self.parse_or()?;
+                if self.next() != Some(Token::CloseParen) {
+                    self.pos -= 1;
+                    return self.error("Expected ')'");
+                }
+                Ok(expr)
+            }
+            Some(Token::Word(word)) => match self.peek() {
+                Some(Token::Operator(op)) => {
+                    let op = *op;
+                    let field = match Field::from_name(&word) {
+                        Some(field) => field,
+                        None => {
+                            return Err(ParseError {
+                                message: format!("Unknown field \"{}\"", word),
+                                position: start,
+                            })
+                        }
+                    };
+                    self.next();
+                    self.parse_condition(field, op)
+                }
+                _ => self.parse_text(word, start),
+            },
+            Some(Token::Quoted(text)) => self.parse_text(text, start),
+            None => {
+                self.pos -= 1;
+                self.error("Unexpected end of query")
+            }
+            Some(_) => {
+                self.pos -= 1;
+                self.error("Expected a condition")
+            }
+        }
+    }
+
+    fn parse_text(&self, text: String, position: usize) -> Result<Expr, ParseError> {
+        Ok(Expr::Text(if self.options.regex {
+            Value::Regex(self.options.build_regex(&text, position)?)
+        } else {
+            Value::Text(self.options.normalize(&text))
+        }))
+    }
+
+    fn parse_condition(&mut self, field: Field, op: Operator) -> Result<Expr, ParseError> {
+        let position = self.position();
+        let text = match self.next() {
+            Some(Token::Word(text)) | Some(Token::Quoted(text)) => text,
+            _ => {
+                self.pos -= 1;
+                return self.error("Expected a value");
+            }
+        };
+        let value = if op == Operator::Match {
+            Value::Regex(self.options.build_regex(&text, position)?)
+        } else if field.is_numeric() {
+            match parse_number(&text) {
+                Some(n) => Value::Number(n),
+                None => {
+                    return Err(ParseError {
+                        message: format!("\"{}\" isn't a number", text),
+                        position,
+                    })
+                }
+            }
+        } else if op == Operator::Equal || op == Operator::NotEqual {
+            Value::Text(self.options.normalize(&text))
+        } else {
+            return Err(ParseError {
+                message: "This operator can only be used on numbers".to_owned(),
+                position,
+            });
+        };
+        Ok(Expr::Condition(field, op, value))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Query {
+    expr: Expr,
+    options: QueryOptions,
+}
+
+impl Query {
+    /// Returns `Ok(None)` if the query is empty.
+    pub fn parse(input: &str, options: QueryOptions) -> Result<Option<Query>, ParseError> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Ok(None);
+        }
+        let mut parser = Parser {
+            options,
+            tokens,
+            pos: 0,
+            len: input.chars().count(),
+        };
+        let expr = parser.parse_or()?;
+        if parser.peek().is_some() {
+            return parser.error("Unexpected ')'");
+        }
+        Ok(Some(Query { expr, options }))
+    }
+
+    pub fn matches(&self, row: &dyn Row) -> bool {
+        self.evaluate(&self.expr, row)
+    }
+
+    fn evaluate(&self, expr: &Expr, row: &dyn Row) -> bool {
+        match expr {
+            Expr::And(left, right) => self.evaluate(left, row) && self.evaluate(right, row),
+            Expr::Or(left, right) => self.evaluate(left, row) || self.evaluate(right, row),
+            Expr::Not(expr) => !self.evaluate(expr, row),
+            Expr::Text(value) => {
+                let fields: &[Field] = if self.options.command_line {
+                    &[Field::Pid, Field::Name, Field::Command, Field::Exe]
+                } else {
+                    &[Field::Pid, Field::Name]
+                };
+                fields.iter().any(|field| {
+                    let text = row.get_text(*field);
+                    match value {
+                        Value::Regex(regex) => regex.is_match(&text),
+                        Value::Text(value) => {
+                            let text = self.options.normalize(&text);
+                            text.contains(value)
+                                || (!text.is_empty()
+                                    && (*field == Field::Pid || *field == Field::Name)
+                                    && value.contains(&text))
+                        }
+                        Value::Number(_) => false,
+                    }
+                })
+            }
+            Expr::Condition(field, op, Value::Number(value)) => match row.get_number(*field) {
+                Some(n) => match op {
+                    Operator::Equal => (n - value).abs() < f64::EPSILON,
+                    Operator::NotEqual => (n - value).abs() >= f64::EPSILON,
+                    Operator::Lower => n < *value,
+                    Operator::LowerOrEqual => n <= *value,
+                    Operator::Greater => n > *value,
+                    Operator::GreaterOrEqual => n >= *value,
+                    Operator::Match => false,
+                },
+                None => false,
+            },
+            Expr::Condition(field, op, Value::Text(value)) => {
+                let equal = self.options.normalize(&row.get_text(*field)) == *value;
+                if *op == Operator::NotEqual {
+                    !equal
+                } else {
+                    equal
+                }
+            }
+            Expr::Condition(field, _, Value::Regex(regex)) => regex.is_match(&row.get_text(*field)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_number, Field, Query, QueryOptions, Row};
+
+    struct Process;
+
+    impl Row for Process {
+        fn get_number(&self, field: Field) -> Option<f64> {
+            match field {
+                Field::Pid => Some(42.),
+                Field::Cpu => Some(10.),
+                Field::Memory => Some(1_500_000.),
+                _ => None,
+            }
+        }
+
+        fn get_text(&self, field: Field) -> String {
+            match field {
+                Field::Pid => "42",
+                Field::Name => "firefox",
+                Field::User => "Alice Smith",
+                Field::Command => "/usr/bin/firefox --private",
+                Field::Exe => "/usr/bin/firefox",
+                _ => "",
+            }
+            .to_owned()
+        }
+    }
+
+    /// A group row of the process list: only the aggregated values of its processes are known.
+    struct Group;
+
+    impl Row for Group {
+        fn get_number(&self, field: Field) -> Option<f64> {
+            match field {
+                Field::Cpu => Some(30.),
+                Field::Memory => Some(4_000_000.),
+                Field::Threads => Some(12.),
+                _ => None,
+            }
+        }
+
+        fn get_text(&self, field: Field) -> String {
+            match field {
+                Field::Cpu => "30.0",
+                Field::Memory => "4.0 MB",
+                Field::Threads => "12",
+                _ => "",
+            }
+            .to_owned()
+        }
+    }
+
+    fn parse(query: &str, options: QueryOptions) -> Query {
+        Query::parse(query, options)
+            .expect("failed to parse query")
+            .expect("empty query")
+    }
+
+    fn matches_with(query: &str, options: QueryOptions) -> bool {
+        parse(query, options).matches(&Process)
+    }
+
+    fn matches(query: &str) -> bool {
+        matches_with(query, QueryOptions::default())
+    }
+
+    /// Returns the message and the position of the error.
+    fn error(query: &str) -> (String, usize) {
+        let e = Query::parse(query, QueryOptions::default()).expect_err("query should be invalid");
+        (e.message, e.position)
+    }
+
+    #[test]
+    fn precedence() {
+        // `AND` has a higher precedence than `OR`.
+        assert!(matches("cpu>5 OR cpu>50 mem>5M"));
+        assert!(matches("cpu>5 || cpu>50 && mem>5M"));
+        // `NOT` only applies to the next condition.
+        assert!(!matches("NOT cpu>50 mem>5M"));
+        assert!(matches("!cpu>50 mem>1M"));
+        assert!(!matches("not not cpu>50"));
+    }
+
+    #[test]
+    fn parentheses() {
+        assert!(!matches("(cpu>5 OR cpu>50) mem>5M"));
+        assert!(matches("!(cpu>50 || mem>5M)"));
+        assert!(matches("((cpu=10)) and (pid=42 or name=chrome)"));
+    }
+
+    #[test]
+    fn quoting() {
+        assert!(matches(r#"user="alice smith""#));
+        assert!(!matches("user=alice"));
+        assert!(!matches(r#"user!="Alice Smith""#));
+        assert!(matches(r#""fire""#));
+        assert!(!matches(r#"name="fire fox""#));
+        assert!(matches_with(
+            r#"name="firefox""#,
+            QueryOptions {
+                case_sensitive: true,
+                ..QueryOptions::default()
+            }
+        ));
+        assert!(!matches_with(
+            "Firefox",
+            QueryOptions {
+                case_sensitive: true,
+                ..QueryOptions::default()
+            }
+        ));
+        assert!(Query::parse(r#"name="a \"b\"""#, QueryOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn text() {
+        assert!(matches("fire"));
+        assert!(matches("42"));
+        // The old filter behaviour: the name is contained in the text.
+        assert!(matches("firefox-esr"));
+        assert!(!matches("chrome"));
+        assert!(!matches("private"));
+        assert!(matches_with(
+            "private",
+            QueryOptions {
+                command_line: true,
+                ..QueryOptions::default()
+            }
+        ));
+        assert!(Query::parse("  ", QueryOptions::default())
+            .expect("failed to parse query")
+            .is_none());
+    }
+
+    #[test]
+    fn group() {
+        let matches_group = |query| parse(query, QueryOptions::default()).matches(&Group);
+
+        // The empty per-process fields must not match through the reverse containment.
+        assert!(!matches_group("8080"));
+        assert!(!matches_group("java10"));
+        assert!(!matches_group("pid<10"));
+        assert!(!matches_group("nice=0"));
+        assert!(!matches_group("ppid=0"));
+        assert!(!matches_group("user=root"));
+        assert!(matches_group("cpu>20"));
+        assert!(matches_group("mem>3M threads=12"));
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_number("20"), Some(20.));
+        assert_eq!(parse_number("1.5"), Some(1.5));
+        assert_eq!(parse_number("20%"), Some(20.));
+        assert_eq!(parse_number("2k"), Some(2_000.));
+        assert_eq!(parse_number("1.5M"), Some(1_500_000.));
+        assert_eq!(parse_number("500MB"), Some(500_000_000.));
+        assert_eq!(parse_number("3g"), Some(3_000_000_000.));
+        assert_eq!(parse_number("1T"), Some(1_000_000_000_000.));
+        assert_eq!(parse_number("k"), None);
+        assert_eq!(parse_number(""), None);
+        assert_eq!(parse_number("abc"), None);
+
+        assert!(matches("cpu>=10%"));
+        assert!(matches("mem>1M"));
+        assert!(!matches("mem<1.5m"));
+        assert!(matches("mem>=1.5MB"));
+        // Unknown values never match.
+        assert!(!matches("nice<100"));
+    }
+
+    #[test]
+    fn regex() {
+        assert!(matches(r#"name~"^fire""#));
+        assert!(!matches("name~^fox"));
+        assert!(matches("user~SMITH$"));
+        let options = QueryOptions {
+            regex: true,
+            ..QueryOptions::default()
+        };
+        assert!(matches_with("f.*x", options));
+        assert!(!matches_with("^refox", options));
+
+        let (message, position) = error(r#"name~"[a""#);
+        assert!(message.starts_with("Invalid regex"), "{}", message);
+        assert_eq!(position, 5);
+        let e = Query::parse(r#"cpu>1 "[a""#, options).expect_err("query should be invalid");
+        assert!(e.message.starts_with("Invalid regex"), "{}", e.message);
+        assert_eq!(e.position, 6);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error(r#"name="foo"#), ("Unclosed quote".to_owned(), 5));
+        assert_eq!(error("cpu>1 &"), ("Unexpected character '&'".to_owned(), 6));
+        assert_eq!(error("bogus>1"), ("Unknown field \"bogus\"".to_owned(), 0));
+        assert_eq!(error("cpu > abc"), ("\"abc\" isn't a number".to_owned(), 6));
+        assert_eq!(
+            error("user<5"),
+            ("This operator can only be used on numbers".to_owned(), 5)
+        );
+        assert_eq!(error("cpu>"), ("Expected a value".to_owned(), 4));
+        assert_eq!(error("(cpu>1"), ("Expected ')'".to_owned(), 6));
+        assert_eq!(error("cpu>1)"), ("Unexpected ')'".to_owned(), 5));
+        assert_eq!(error("cpu>1 OR"), ("Unexpected end of query".to_owned(), 8));
+        assert_eq!(
+            error("cpu>1 AND )"),
+            ("Expected a condition".to_owned(), 10)
+        );
+    }
+}
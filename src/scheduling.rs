@@ -0,0 +1,228 @@
+use gtk;
+use gtk::prelude::{
+    ContainerExt, DialogExt, EntryExt, GtkWindowExt, LabelExt, SpinButtonExt, WidgetExt,
+};
+use libc::c_int;
+use sysinfo::Pid;
+
+use std::io;
+#[cfg(target_os = "linux")]
+use std::mem;
+
+pub const MIN_NICE: c_int = -20;
+pub const MAX_NICE: c_int = 19;
+
+#[cfg(target_os = "linux")]
+unsafe fn errno_location() -> *mut c_int {
+    libc::__errno_location()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+unsafe fn errno_location() -> *mut c_int {
+    libc::__error()
+}
+
+/// Turns the error into something understandable for the user.
+pub fn describe_error(e: &io::Error) -> String {
+    match e.kind() {
+        io::ErrorKind::PermissionDenied => format!(
+            "{} (lowering the nice value or changing a process from another user requires \
+             administrator privileges)",
+            e
+        ),
+        _ => e.to_string(),
+    }
+}
+
+/// Same as `describe_error` but for errors returned by `set_affinity`.
+pub fn describe_affinity_error(e: &io::Error) -> String {
+    match e.kind() {
+        io::ErrorKind::PermissionDenied => format!(
+            "{} (changing the CPU affinity of a process from another user requires \
+             administrator privileges)",
+            e
+        ),
+        _ => e.to_string(),
+    }
+}
+
+/// Returns the number of processors which can be used in an affinity mask.
+#[cfg(unix)]
+pub fn get_cpu_count() -> usize {
+    let count = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_CONF) };
+    if count > 0 {
+        count as usize
+    } else {
+        1
+    }
+}
+
+#[cfg(windows)]
+pub fn get_cpu_count() -> usize {
+    1
+}
+
+/// Returns, for each processor, whether the process is allowed to run on it.
+#[cfg(target_os = "linux")]
+pub fn get_affinity(pid: Pid) -> io::Result<Vec<bool>> {
+    unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        if libc::sched_getaffinity(
+            pid as libc::pid_t,
+            mem::size_of::<libc::cpu_set_t>(),
+            &mut set,
+        ) != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((0..get_cpu_count())
+            .map(|cpu| libc::CPU_ISSET(cpu, &set))
+            .collect())
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn set_affinity(pid: Pid, cpus: &[bool]) -> io::Result<()> {
+    if !cpus.iter().any(|allowed| *allowed) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "at least one processor has to be selected",
+        ));
+    }
+    unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for (cpu, _) in cpus.iter().enumerate().filter(|(_, allowed)| **allowed) {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        if libc::sched_setaffinity(pid as libc::pid_t, mem::size_of::<libc::cpu_set_t>(), &set) != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_affinity(_pid: Pid) -> io::Result<Vec<bool>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_affinity(_pid: Pid, _cpus: &[bool]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+#[cfg(unix)]
+pub fn get_priority(pid: Pid) -> io::Result<c_int> {
+    unsafe {
+        // `getpriority` can return -1 on success so we have to rely on errno.
+        *errno_location() = 0;
+        let nice = libc::getpriority(libc::PRIO_PROCESS as _, pid as libc::id_t);
+        if nice == -1 && *errno_location() != 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(nice)
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn set_priority(pid: Pid, nice: c_int) -> io::Result<()> {
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS as _, pid as libc::id_t, nice) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(windows)]
+pub fn get_priority(_pid: Pid) -> io::Result<c_int> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+#[cfg(windows)]
+pub fn set_priority(_pid: Pid, _nice: c_int) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "not supported on this platform",
+    ))
+}
+
+/// Changes the nice value of all the given processes. In both cases, it returns the message to
+/// display to the user.
+pub fn set_priorities(pids: &[Pid], nice: c_int) -> Result<String, String> {
+    let errors = pids
+        .iter()
+        .filter_map(|pid| {
+            set_priority(*pid, nice).err().map(|e| {
+                format!(
+                    "Failed to change priority of process {}: {}",
+                    pid,
+                    describe_error(&e)
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        Err(errors.join("\n"))
+    } else if pids.len() == 1 {
+        Ok(format!("Priority of process {} set to {}", pids[0], nice))
+    } else {
+        Ok(format!(
+            "Priority of {} processes set to {}",
+            pids.len(),
+            nice
+        ))
+    }
+}
+
+/// Asks the user for a new nice value and then calls `callback` with it.
+pub fn show_renice_dialog<W, F>(window: &W, current: c_int, callback: F)
+where
+    W: glib::IsA<gtk::Window>,
+    F: Fn(c_int) + 'static,
+{
+    let dialog = gtk::Dialog::new_with_buttons(
+        Some("Change priority"),
+        Some(window),
+        gtk::DialogFlags::MODAL,
+        &[
+            ("Apply", gtk::ResponseType::Ok),
+            ("Cancel", gtk::ResponseType::Close),
+        ],
+    );
+    let label = gtk::Label::new(None);
+    let spin = gtk::SpinButton::new_with_range(f64::from(MIN_NICE), f64::from(MAX_NICE), 1.);
+
+    label.set_markup(
+        "Nice value (from <b>-20</b>, highest priority, to <b>19</b>, lowest priority):",
+    );
+    spin.set_value(f64::from(current));
+
+    let content_area = dialog.get_content_area();
+    content_area.set_border_width(10);
+    content_area.add(&label);
+    content_area.add(&spin);
+
+    spin.connect_activate(clone!(@weak dialog => move |_| {
+        dialog.response(gtk::ResponseType::Ok);
+    }));
+    dialog.connect_response(move |dialog, response| {
+        if response == gtk::ResponseType::Ok {
+            callback(spin.get_value_as_int());
+        }
+        dialog.close();
+    });
+    dialog.set_resizable(false);
+    dialog.show_all();
+}
@@ -9,8 +9,8 @@ use gtk;
 
 use gio::ApplicationExt;
 use gtk::{
-    BoxExt, ContainerExt, DialogExt, GridExt, GtkWindowExt, SpinButtonExt, SpinButtonSignals,
-    WidgetExt,
+    BoxExt, ContainerExt, DialogExt, EditableSignals, EntryExt, GridExt, GtkWindowExt,
+    SpinButtonExt, SpinButtonSignals, WidgetExt,
 };
 
 use std::cell::RefCell;
@@ -19,11 +19,14 @@ use std::io::Read;
 use std::path::PathBuf;
 use std::rc::Rc;
 
+use display_procs::GroupBy;
 use utils::{get_app, get_main_window};
 use RequiredForSettings;
 use APPLICATION_NAME;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+// Missing fields are taken from `Settings::default()` so older settings files can still be read.
+#[serde(default)]
 pub struct Settings {
     pub display_fahrenheit: bool,
     pub display_graph: bool,
@@ -33,6 +36,38 @@ pub struct Settings {
     pub refresh_system_rate: u32,
     // Timer length in milliseconds (500 minimum!).
     pub refresh_network_rate: u32,
+    // Names of the processes which cannot be killed (or receive any signal) from the UI.
+    pub protected_processes: Vec<String>,
+    // Users whose processes cannot be killed (or receive any signal) from the UI.
+    pub protected_users: Vec<String>,
+    // Whether the process list is displayed as a tree.
+    pub process_tree_mode: bool,
+    // How the processes are grouped in the process list, if they are.
+    pub process_group_by: Option<GroupBy>,
+    // Columns of the process list.
+    pub process_columns: TreeViewSettings,
+    // Columns of the network list.
+    pub network_columns: TreeViewSettings,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ColumnSettings {
+    pub title: String,
+    pub visible: bool,
+    // Width in pixels.
+    pub width: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TreeViewSettings {
+    // Title of the column used to sort the rows. It was the index of the model column before so
+    // it uses another name to ignore the old value.
+    #[serde(rename = "sort_column_title")]
+    pub sort_column: Option<String>,
+    pub sort_ascending: bool,
+    // In display order.
+    pub columns: Vec<ColumnSettings>,
 }
 
 impl Default for Settings {
@@ -43,6 +78,12 @@ impl Default for Settings {
             refresh_processes_rate: 1500,
             refresh_system_rate: 2000,
             refresh_network_rate: 1500,
+            protected_processes: vec!["init".to_owned(), "systemd".to_owned()],
+            protected_users: Vec::new(),
+            process_tree_mode: false,
+            process_group_by: None,
+            process_columns: TreeViewSettings::default(),
+            network_columns: TreeViewSettings::default(),
         }
     }
 }
@@ -72,6 +113,11 @@ impl Settings {
         }
     }
 
+    pub fn is_protected(&self, process_name: &str, user: &str) -> bool {
+        self.protected_processes.iter().any(|p| p == process_name)
+            || self.protected_users.iter().any(|u| u == user)
+    }
+
     pub fn get_settings_file_path() -> PathBuf {
         let mut path = glib::get_user_config_dir().unwrap_or_else(|| PathBuf::from("."));
         path.push(APPLICATION_NAME);
@@ -118,7 +164,7 @@ impl Settings {
     }
 }
 
-fn show_error_dialog(fatal: bool, text: &str) {
+pub fn show_error_dialog(fatal: bool, text: &str) {
     let dialog = gtk::MessageDialog::new(
         get_main_window().as_ref(),
         gtk::DialogFlags::MODAL,
@@ -155,6 +201,34 @@ pub fn build_spin(label: &str, grid: &gtk::Grid, top: i32, refresh: u32) -> gtk:
     refresh_entry
 }
 
+fn build_list_entry(label: &str, grid: &gtk::Grid, top: i32, values: &[String]) -> gtk::Entry {
+    let list_label = gtk::Label::new(Some(label));
+    let list_entry = gtk::Entry::new();
+
+    list_label.set_halign(gtk::Align::Start);
+    list_entry.set_hexpand(true);
+    list_entry.set_placeholder_text(Some("comma separated"));
+
+    list_entry.set_text(&values.join(", "));
+
+    grid.attach(&list_label, 0, top, 1, 1);
+    grid.attach(&list_entry, 1, top, 3, 1);
+    list_entry
+}
+
+fn parse_list(entry: &gtk::Entry) -> Vec<String> {
+    entry
+        .get_text()
+        .map(|text| {
+            text.split(',')
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 pub fn show_settings_dialog(
     settings: &Rc<RefCell<Settings>>,
     rfs: &Rc<RefCell<RequiredForSettings>>,
@@ -192,6 +266,13 @@ pub fn show_settings_dialog(
         2,
         bsettings.refresh_system_rate,
     );
+    let protected_processes = build_list_entry(
+        "Protected processes",
+        &grid,
+        3,
+        &bsettings.protected_processes,
+    );
+    let protected_users = build_list_entry("Protected users", &grid, 4, &bsettings.protected_users);
 
     // Put the grid into the dialog's content area.
     let content_area = dialog.get_content_area();
@@ -219,6 +300,17 @@ pub fn show_settings_dialog(
         settings.save();
     }));
 
+    protected_processes.connect_changed(clone!(@weak settings => move |entry| {
+        let mut settings = settings.borrow_mut();
+        settings.protected_processes = parse_list(entry);
+        settings.save();
+    }));
+    protected_users.connect_changed(clone!(@weak settings => move |entry| {
+        let mut settings = settings.borrow_mut();
+        settings.protected_users = parse_list(entry);
+        settings.save();
+    }));
+
     dialog.connect_response(move |dialog, _| {
         dialog.close();
     });
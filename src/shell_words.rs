@@ -0,0 +1,236 @@
+//! Splits command lines into words the way a POSIX shell does. Quotes, backslash escapes, `~` and
+//! `$VAR` expansions are supported, but not the other shell features (redirections, pipes,
+//! globs...): the characters they use are kept as is.
+
+use std::env;
+use std::iter::Peekable;
+use std::mem;
+use std::str::Chars;
+
+fn read_variable_name(chars: &mut Peekable<Chars>) -> String {
+    let mut name = String::new();
+
+    while let Some(&c) = chars.peek() {
+        if c == '_' || c.is_ascii_alphabetic() || (!name.is_empty() && c.is_ascii_digit()) {
+            name.push(c);
+            chars.next();
+        } else {
+            break;
+        }
+    }
+    name
+}
+
+/// Expands the variable following a `$`. Unset variables are expanded to an empty string. It
+/// returns `None` if the `$` isn't followed by a variable name, in which case it's kept as is.
+fn expand_variable<F>(chars: &mut Peekable<Chars>, get_var: &F) -> Result<Option<String>, String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if chars.peek() == Some(&'{') {
+        chars.next();
+        let name = read_variable_name(chars);
+        return match chars.next() {
+            Some('}') if !name.is_empty() => Ok(Some(get_var(&name).unwrap_or_default())),
+            Some(_) => Err("invalid variable name in ${...}".to_owned()),
+            None => Err("missing '}'".to_owned()),
+        };
+    }
+    let name = read_variable_name(chars);
+    if name.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(get_var(&name).unwrap_or_default()))
+    }
+}
+
+/// Splits the line into words, using `get_var` to expand the variables (and `HOME` for `~`).
+pub fn split_with<F>(line: &str, get_var: F) -> Result<Vec<String>, String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut words = Vec::new();
+    let mut word = String::new();
+    // A word can be empty (`""` for example), so we cannot rely on `word.is_empty()`.
+    let mut in_word = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                if in_word {
+                    words.push(mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("missing closing single quote".to_owned()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // Inside double quotes, backslashes only escape these characters.
+                        Some('\\') => match chars.next() {
+                            Some(c @ '$') | Some(c @ '`') | Some(c @ '"') | Some(c @ '\\') => {
+                                word.push(c)
+                            }
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("missing closing double quote".to_owned()),
+                        },
+                        Some('$') => match expand_variable(&mut chars, &get_var)? {
+                            Some(value) => word.push_str(&value),
+                            None => word.push('$'),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("missing closing double quote".to_owned()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                // Line continuation.
+                Some('\n') => {}
+                Some(c) => {
+                    word.push(c);
+                    in_word = true;
+                }
+                None => return Err("nothing to escape after the last backslash".to_owned()),
+            },
+            '$' => match expand_variable(&mut chars, &get_var)? {
+                // Like in a shell, an unquoted empty variable doesn't create a word.
+                Some(value) => {
+                    in_word |= !value.is_empty();
+                    word.push_str(&value);
+                }
+                None => {
+                    word.push('$');
+                    in_word = true;
+                }
+            },
+            '~' if !in_word => match chars.peek() {
+                None | Some('/') | Some(' ') | Some('\t') | Some('\n') => {
+                    word.push_str(&get_var("HOME").unwrap_or_else(|| "~".to_owned()));
+                    in_word = true;
+                }
+                // `~user` isn't supported.
+                Some(_) => {
+                    word.push('~');
+                    in_word = true;
+                }
+            },
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Splits the line into words. Variables are looked up in `environment` first, then in the
+/// environment of process-viewer.
+pub fn split(line: &str, environment: &[(String, String)]) -> Result<Vec<String>, String> {
+    split_with(line, |name| {
+        environment
+            .iter()
+            .rev()
+            .find(|(var, _)| var == name)
+            .map(|(_, value)| value.clone())
+            .or_else(|| env::var(name).ok())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_with;
+
+    fn split(line: &str) -> Result<Vec<String>, String> {
+        split_with(line, |name| match name {
+            "HOME" => Some("/home/me".to_owned()),
+            "VAR" => Some("value".to_owned()),
+            "SPACES" => Some("a b".to_owned()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        })
+    }
+
+    fn words(line: &str) -> Vec<String> {
+        split(line).expect("failed to split")
+    }
+
+    #[test]
+    fn whitespace() {
+        assert_eq!(words("foo bar"), ["foo", "bar"]);
+        assert_eq!(words("foo  bar"), ["foo", "bar"]);
+        assert_eq!(words("  foo\tbar\n"), ["foo", "bar"]);
+        assert!(words("").is_empty());
+        assert!(words("   ").is_empty());
+    }
+
+    #[test]
+    fn quotes() {
+        assert_eq!(words(r#"foo "a b" 'c d'"#), ["foo", "a b", "c d"]);
+        assert_eq!(words(r#"a"b c"'d e'f"#), ["ab cd ef"]);
+        assert_eq!(words(r#"'"' "'""#), ["\"", "'"]);
+        assert_eq!(words(r#"foo "" ''"#), ["foo", "", ""]);
+        assert_eq!(words("'a\nb'"), ["a\nb"]);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(words(r"a\ b c"), ["a b", "c"]);
+        assert_eq!(words(r#"\"a\'"#), ["\"a'"]);
+        assert_eq!(words(r"'a\b'"), [r"a\b"]);
+        assert_eq!(words(r#""a\"b\\c\d\$""#), [r#"a"b\c\d$"#]);
+        assert_eq!(words("a\\\nb"), ["ab"]);
+        assert_eq!(words(r"\\"), [r"\"]);
+        assert_eq!(words(r"\~"), ["~"]);
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(words("$VAR ${VAR}x"), ["value", "valuex"]);
+        assert_eq!(words(r#""$VAR-$SPACES""#), ["value-a b"]);
+        assert_eq!(words("'$VAR'"), ["$VAR"]);
+        assert_eq!(words(r"\$VAR"), ["$VAR"]);
+        // `VAR_2` is unset.
+        assert!(words("$VAR_2").is_empty());
+        assert_eq!(words("a $UNSET b $EMPTY"), ["a", "b"]);
+        assert_eq!(words(r#""$UNSET""#), [""]);
+        assert_eq!(words("$ a$ $1"), ["$", "a$", "$1"]);
+        // No field splitting: the value stays in one word.
+        assert_eq!(words("$SPACES"), ["a b"]);
+    }
+
+    #[test]
+    fn tilde() {
+        assert_eq!(words("~ ~/bin"), ["/home/me", "/home/me/bin"]);
+        assert_eq!(words("a~ '~' \"~\" ~user"), ["a~", "~", "~", "~user"]);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(split("'foo").is_err());
+        assert!(split("\"foo").is_err());
+        assert!(split("\"foo\\").is_err());
+        assert!(split("foo\\").is_err());
+        assert!(split("${VAR").is_err());
+        assert!(split("${}").is_err());
+        assert!(split("${VAR-x}").is_err());
+    }
+}
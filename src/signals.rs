@@ -0,0 +1,585 @@
+use gio;
+use glib::ToVariant;
+use gtk;
+use gtk::prelude::{
+    BoxExt, CellLayoutExt, ContainerExt, DialogExt, GtkListStoreExtManual, GtkWindowExt, LabelExt,
+    ScrolledWindowExt, SpinButtonExt, SpinButtonSignals, TreeViewColumnExt, TreeViewExt, WidgetExt,
+};
+use libc::c_int;
+use sysinfo::{self, AsU32, Pid, ProcessExt, SystemExt};
+
+use settings::Settings;
+use utils::{get_process_user, ProcessIdentity};
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+pub use libc::{SIGCONT, SIGKILL, SIGSTOP};
+#[cfg(windows)]
+pub const SIGKILL: c_int = 9;
+// They cannot be sent on Windows but it allows to use them in the UI code.
+#[cfg(windows)]
+pub const SIGSTOP: c_int = 19;
+#[cfg(windows)]
+pub const SIGCONT: c_int = 18;
+
+/// The state displayed for the stopped processes (by `SIGSTOP` for example).
+pub const STOPPED_STATE: &str = "Stopped";
+
+/// The signals which can be sent from the process list: (label, name, value).
+#[cfg(unix)]
+pub const SIGNALS: &[(&str, &str, c_int)] = &[
+    ("Terminate", "SIGTERM", libc::SIGTERM),
+    ("Interrupt", "SIGINT", libc::SIGINT),
+    ("Hang up", "SIGHUP", libc::SIGHUP),
+    ("Stop", "SIGSTOP", libc::SIGSTOP),
+    ("Continue", "SIGCONT", libc::SIGCONT),
+    ("User defined 1", "SIGUSR1", libc::SIGUSR1),
+    ("User defined 2", "SIGUSR2", libc::SIGUSR2),
+    ("Kill", "SIGKILL", libc::SIGKILL),
+];
+#[cfg(windows)]
+pub const SIGNALS: &[(&str, &str, c_int)] = &[("Kill", "SIGKILL", SIGKILL)];
+
+pub fn get_signal_name(signal: c_int) -> String {
+    SIGNALS
+        .iter()
+        .find(|(_, _, value)| *value == signal)
+        .map(|(_, name, _)| (*name).to_owned())
+        .unwrap_or_else(|| format!("signal {}", signal))
+}
+
+#[cfg(unix)]
+fn send_signal(process: &sysinfo::Process, signal: c_int) -> io::Result<()> {
+    if unsafe { libc::kill(process.pid() as libc::pid_t, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(windows)]
+fn send_signal(process: &sysinfo::Process, signal: c_int) -> io::Result<()> {
+    if signal != SIGKILL {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "not supported on this platform",
+        ));
+    }
+    // There is no signal on Windows, the process is simply terminated.
+    if process.kill(sysinfo::Signal::Kill) {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Sends `signal` to the given process. In both cases, it returns the message to display to
+/// the user.
+pub fn signal_process(
+    sys: &sysinfo::System,
+    settings: &Settings,
+    target: &ProcessIdentity,
+    signal: c_int,
+) -> Result<String, String> {
+    let pid = target.pid;
+    let process = sys
+        .get_process(pid)
+        .ok_or_else(|| format!("Process {} doesn't exist anymore", pid))?;
+    let signal_name = get_signal_name(signal);
+    if !target.is_same_process(process) {
+        return Err(format!(
+            "Process {} doesn't exist anymore and its PID is now used by \"{}\": {} not sent",
+            pid,
+            process.name(),
+            signal_name
+        ));
+    }
+    if settings.is_protected(process.name(), &get_process_user(process)) {
+        return Err(format!(
+            "\"{}\" (pid {}) is protected: {} not sent",
+            process.name(),
+            pid,
+            signal_name
+        ));
+    }
+    match send_signal(process, signal) {
+        Ok(()) => Ok(format!(
+            "{} sent to \"{}\" (pid {})",
+            signal_name,
+            process.name(),
+            pid
+        )),
+        Err(e) => Err(format!(
+            "Failed to send {} to \"{}\" (pid {}): {}",
+            signal_name,
+            process.name(),
+            pid,
+            e
+        )),
+    }
+}
+
+/// Sends `signal` to all the given processes, in the given order.
+pub fn signal_processes(
+    sys: &sysinfo::System,
+    settings: &Settings,
+    targets: &[ProcessIdentity],
+    signal: c_int,
+    stopped: &mut StoppedProcesses,
+) -> Result<String, String> {
+    let mut messages = Vec::new();
+    let mut errors = Vec::new();
+
+    for target in targets {
+        match signal_process(sys, settings, target, signal) {
+            Ok(msg) => {
+                stopped.signal_sent(sys, target, signal);
+                messages.push(msg);
+            }
+            Err(msg) => errors.push(msg),
+        }
+    }
+    if !errors.is_empty() {
+        Err(errors.join("\n"))
+    } else if messages.len() == 1 {
+        Ok(messages.remove(0))
+    } else {
+        Ok(format!(
+            "{} sent to {} processes",
+            get_signal_name(signal),
+            messages.len()
+        ))
+    }
+}
+
+/// The process list can be refreshed before a stopped process actually stopped, so a process is
+/// only considered resumed if it's running after this delay.
+const STOP_DELAY: Duration = Duration::from_secs(5);
+
+struct StoppedProcess {
+    identity: ProcessIdentity,
+    name: String,
+    stopped_at: Instant,
+}
+
+/// The processes stopped from process-viewer. They're listed in a menu so the user doesn't forget
+/// to resume them.
+pub struct StoppedProcesses {
+    processes: Vec<StoppedProcess>,
+    menu: gio::Menu,
+}
+
+impl StoppedProcesses {
+    pub fn new(menu: gio::Menu) -> StoppedProcesses {
+        let stopped = StoppedProcesses {
+            processes: Vec::new(),
+            menu,
+        };
+        stopped.update_menu();
+        stopped
+    }
+
+    pub fn get(&self, pid: Pid) -> Option<&ProcessIdentity> {
+        self.processes
+            .iter()
+            .map(|p| &p.identity)
+            .find(|identity| identity.pid == pid)
+    }
+
+    pub fn get_all(&self) -> Vec<ProcessIdentity> {
+        self.processes.iter().map(|p| p.identity.clone()).collect()
+    }
+
+    /// Called once `signal` has been sent to the process.
+    fn signal_sent(&mut self, sys: &sysinfo::System, target: &ProcessIdentity, signal: c_int) {
+        if signal != SIGSTOP && signal != SIGCONT && signal != SIGKILL {
+            return;
+        }
+        self.processes.retain(|p| p.identity.pid != target.pid);
+        if signal == SIGSTOP {
+            self.processes.push(StoppedProcess {
+                identity: target.clone(),
+                name: sys
+                    .get_process(target.pid)
+                    .map(|p| p.name().to_owned())
+                    .unwrap_or_default(),
+                stopped_at: Instant::now(),
+            });
+        }
+        self.update_menu();
+    }
+
+    /// Removes the processes which are dead or have been resumed by something else.
+    pub fn update(&mut self, sys: &sysinfo::System) {
+        let count = self.processes.len();
+        self.processes
+            .retain(|stopped| match sys.get_process(stopped.identity.pid) {
+                Some(p) if stopped.identity.is_same_process(p) => {
+                    p.status().to_string() == STOPPED_STATE
+                        || stopped.stopped_at.elapsed() < STOP_DELAY
+                }
+                _ => false,
+            });
+        if count != self.processes.len() {
+            self.update_menu();
+        }
+    }
+
+    fn update_menu(&self) {
+        self.menu.remove_all();
+        if self.processes.is_empty() {
+            // No action so it's displayed as disabled.
+            self.menu.append(Some("No stopped process"), None);
+            return;
+        }
+        for stopped in &self.processes {
+            let item = gio::MenuItem::new(
+                Some(&format!(
+                    "Resume \"{}\" (pid {})",
+                    stopped.name, stopped.identity.pid
+                )),
+                None,
+            );
+            item.set_action_and_target_value(
+                Some("app.resume-process"),
+                Some(&(stopped.identity.pid.as_u32() as i32).to_variant()),
+            );
+            self.menu.append_item(&item);
+        }
+        let resume_all = gio::Menu::new();
+        resume_all.append(Some("Resume all"), Some("app.resume-all"));
+        self.menu.append_section(None, &resume_all);
+    }
+}
+
+fn fill_subtree(
+    children: &HashMap<Pid, Vec<Pid>>,
+    pid: Pid,
+    seen: &mut HashSet<Pid>,
+    pids: &mut Vec<Pid>,
+) {
+    if !seen.insert(pid) {
+        return;
+    }
+    if let Some(children_pids) = children.get(&pid) {
+        for child in children_pids {
+            fill_subtree(children, *child, seen, pids);
+        }
+    }
+    pids.push(pid);
+}
+
+/// Returns the children of the processes from `(pid, parent)` pairs.
+fn get_children<I: IntoIterator<Item = (Pid, Option<Pid>)>>(
+    processes: I,
+) -> HashMap<Pid, Vec<Pid>> {
+    let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+
+    for (child, parent) in processes {
+        if let Some(parent) = parent {
+            children.entry(parent).or_default().push(child);
+        }
+    }
+    children
+}
+
+/// Returns the given process and all its descendants, leaves first.
+pub fn get_process_subtree(sys: &sysinfo::System, pid: Pid) -> Vec<Pid> {
+    let children = get_children(
+        sys.get_processes()
+            .iter()
+            .map(|(pid, process)| (*pid, process.parent())),
+    );
+    let mut pids = Vec::new();
+    fill_subtree(&children, pid, &mut HashSet::new(), &mut pids);
+    pids
+}
+
+/// Adds the processes of `subtree` which aren't already in `pids`. It's used when a selected
+/// process is a descendant of another selected one, in which case its subtree is already in
+/// `pids` with the leaves first.
+pub fn merge_subtree(pids: &mut Vec<Pid>, subtree: Vec<Pid>) {
+    for pid in subtree {
+        if !pids.contains(&pid) {
+            pids.push(pid);
+        }
+    }
+}
+
+/// Builds the menu listing the signals which can be sent to the selected processes and the other
+/// actions which can be run on them.
+pub fn build_process_menu() -> gio::Menu {
+    let menu = gio::Menu::new();
+
+    for (label, name, signal) in SIGNALS {
+        menu.append(
+            Some(&format!("{} ({})", label, name)),
+            Some(&format!("app.send-signal({})", signal)),
+        );
+    }
+    menu.append(
+        Some("Send another signal..."),
+        Some("app.send-custom-signal"),
+    );
+
+    let subtree_section = gio::Menu::new();
+    subtree_section.append(Some("End task and children"), Some("app.kill-subtree"));
+    menu.append_section(None, &subtree_section);
+
+    let priority_section = gio::Menu::new();
+    priority_section.append(Some("Change priority..."), Some("app.renice"));
+    menu.append_section(None, &priority_section);
+    menu
+}
+
+/// Asks the user which signal should be sent and then calls `callback` with it.
+pub fn show_custom_signal_dialog<W, F>(window: &W, callback: F)
+where
+    W: glib::IsA<gtk::Window>,
+    F: Fn(c_int) + 'static,
+{
+    let dialog = gtk::Dialog::new_with_buttons(
+        Some("Send signal"),
+        Some(window),
+        gtk::DialogFlags::MODAL,
+        &[
+            ("Send", gtk::ResponseType::Ok),
+            ("Cancel", gtk::ResponseType::Close),
+        ],
+    );
+    let spin = gtk::SpinButton::new_with_range(1., 64., 1.);
+    let label = gtk::Label::new(Some(&get_signal_name(1)));
+
+    spin.set_value(1.);
+    spin.connect_value_changed(clone!(@weak label => move |spin| {
+        label.set_text(&get_signal_name(spin.get_value_as_int()));
+    }));
+
+    let content_area = dialog.get_content_area();
+    content_area.set_border_width(10);
+    content_area.add(&spin);
+    content_area.add(&label);
+
+    dialog.connect_response(move |dialog, response| {
+        if response == gtk::ResponseType::Ok {
+            callback(spin.get_value_as_int());
+        }
+        dialog.close();
+    });
+    dialog.set_resizable(false);
+    dialog.show_all();
+}
+
+/// The information displayed to the user before sending a signal to a process.
+pub struct SignalTarget {
+    pub pid: Pid,
+    pub name: String,
+    pub user: String,
+    pub cmd: String,
+    pub is_protected: bool,
+}
+
+impl SignalTarget {
+    pub fn new(process: &sysinfo::Process, settings: &Settings) -> SignalTarget {
+        let user = get_process_user(process);
+        SignalTarget {
+            pid: process.pid(),
+            name: process.name().to_owned(),
+            is_protected: settings.is_protected(process.name(), &user),
+            user,
+            cmd: process.cmd().join(" "),
+        }
+    }
+}
+
+/// Lists the processes which are about to receive `signal` and calls `callback` if the user
+/// confirms.
+pub fn show_signal_confirmation_dialog<W, F>(
+    window: &W,
+    signal: c_int,
+    targets: &[SignalTarget],
+    callback: F,
+) where
+    W: glib::IsA<gtk::Window>,
+    F: Fn() + 'static,
+{
+    let signal_name = get_signal_name(signal);
+    let dialog = gtk::Dialog::new_with_buttons(
+        Some(&format!("Send {}", signal_name)),
+        Some(window),
+        gtk::DialogFlags::MODAL,
+        &[
+            ("Send", gtk::ResponseType::Ok),
+            ("Cancel", gtk::ResponseType::Close),
+        ],
+    );
+    let label = gtk::Label::new(Some(&if targets.len() == 1 {
+        format!(
+            "{} is going to be sent to the following process:",
+            signal_name
+        )
+    } else {
+        format!(
+            "{} is going to be sent to the following {} processes:",
+            signal_name,
+            targets.len()
+        )
+    }));
+    let tree = gtk::TreeView::new();
+    let list_store = gtk::ListStore::new(&[
+        glib::Type::U32,
+        glib::Type::String,
+        glib::Type::String,
+        glib::Type::String,
+    ]);
+    let scroll = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+
+    for target in targets {
+        let name = if target.is_protected {
+            format!("{} (protected)", target.name)
+        } else {
+            target.name.clone()
+        };
+        list_store.insert_with_values(
+            None,
+            &[0, 1, 2, 3],
+            &[&(target.pid as u32), &name, &target.user, &target.cmd],
+        );
+    }
+    tree.set_model(Some(&list_store));
+    for (pos, title) in ["pid", "name", "user", "command line"].iter().enumerate() {
+        let column = gtk::TreeViewColumn::new();
+        let cell = gtk::CellRendererText::new();
+
+        column.set_title(title);
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "text", pos as i32);
+        tree.append_column(&column);
+    }
+
+    label.set_halign(gtk::Align::Start);
+    scroll.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+    scroll.set_min_content_height(if targets.len() > 1 { 200 } else { 50 });
+    scroll.add(&tree);
+
+    let content_area = dialog.get_content_area();
+    content_area.set_border_width(10);
+    content_area.set_spacing(5);
+    content_area.add(&label);
+    content_area.pack_start(&scroll, true, true, 0);
+    if targets.iter().any(|t| t.is_protected) {
+        content_area.add(&gtk::Label::new(Some(
+            "Protected processes will be skipped.",
+        )));
+    }
+
+    dialog.connect_response(move |dialog, response| {
+        if response == gtk::ResponseType::Ok {
+            callback();
+        }
+        dialog.close();
+    });
+    dialog.set_default_size(500, -1);
+    dialog.show_all();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fill_subtree, get_children, merge_subtree};
+    use std::collections::HashSet;
+    use sysinfo::Pid;
+
+    fn get_subtree(processes: &[(Pid, Option<Pid>)], pid: Pid) -> Vec<Pid> {
+        let mut pids = Vec::new();
+        fill_subtree(
+            &get_children(processes.iter().cloned()),
+            pid,
+            &mut HashSet::new(),
+            &mut pids,
+        );
+        pids
+    }
+
+    /// Checks that every process comes after all its descendants.
+    fn assert_leaves_first(processes: &[(Pid, Option<Pid>)], pids: &[Pid]) {
+        for (pos, pid) in pids.iter().enumerate() {
+            let mut parent = processes.iter().find(|(p, _)| p == pid).and_then(|p| p.1);
+            while let Some(ancestor) = parent {
+                if let Some(ancestor_pos) = pids.iter().position(|p| *p == ancestor) {
+                    assert!(ancestor_pos > pos, "{} is killed before {}", ancestor, pid);
+                }
+                parent = processes
+                    .iter()
+                    .find(|(p, _)| *p == ancestor)
+                    .and_then(|p| p.1);
+            }
+        }
+    }
+
+    //   1
+    //   ├── 2
+    //   │   ├── 4
+    //   │   │   └── 7
+    //   │   └── 5
+    //   └── 3
+    //       └── 6
+    // 8 (unrelated)
+    const TREE: &[(Pid, Option<Pid>)] = &[
+        (1, None),
+        (2, Some(1)),
+        (3, Some(1)),
+        (4, Some(2)),
+        (5, Some(2)),
+        (6, Some(3)),
+        (7, Some(4)),
+        (8, None),
+    ];
+
+    #[test]
+    fn leaves_first() {
+        assert_eq!(get_subtree(TREE, 2), vec![7, 4, 5, 2]);
+        assert_eq!(get_subtree(TREE, 7), vec![7]);
+        assert_eq!(get_subtree(TREE, 9), vec![9]);
+
+        let pids = get_subtree(TREE, 1);
+        let mut sorted = pids.clone();
+        sorted.sort();
+        assert_eq!(sorted, vec![1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(pids.last(), Some(&1));
+        assert_leaves_first(TREE, &pids);
+    }
+
+    #[test]
+    fn parent_cycle() {
+        // It can't happen with the real processes but the parents are read at different times.
+        let processes = &[(1, Some(3)), (2, Some(1)), (3, Some(2)), (4, Some(3))];
+        let mut pids = get_subtree(processes, 1);
+        assert_eq!(pids.last(), Some(&1));
+        pids.sort();
+        assert_eq!(pids, vec![1, 2, 3, 4]);
+
+        assert_eq!(get_subtree(&[(1, Some(1))], 1), vec![1]);
+    }
+
+    #[test]
+    fn merge() {
+        // A selected process which is a descendant of another selected one.
+        for selected in &[[1, 4], [4, 1]] {
+            let mut pids = Vec::new();
+            for pid in selected {
+                merge_subtree(&mut pids, get_subtree(TREE, *pid));
+            }
+            let mut sorted = pids.clone();
+            sorted.sort();
+            assert_eq!(sorted, vec![1, 2, 3, 4, 5, 6, 7]);
+            assert_leaves_first(TREE, &pids);
+        }
+
+        let mut pids = get_subtree(TREE, 3);
+        merge_subtree(&mut pids, get_subtree(TREE, 8));
+        assert_eq!(pids, vec![6, 3, 8]);
+    }
+}
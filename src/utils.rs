@@ -3,11 +3,22 @@ use graph::Graph;
 use gdk_pixbuf::Pixbuf;
 use gio::{self, MemoryInputStream};
 use glib::{Bytes, Cast};
-use gtk::{ButtonExt, GtkApplicationExt, Inhibit, WidgetExt};
+use gtk::{
+    ButtonExt, ContainerExt, DialogExt, FileChooserExt, GtkApplicationExt, InfoBarExt, Inhibit,
+    LabelExt, WidgetExt,
+};
+use sysinfo::{Pid, ProcessExt};
 
 use std::cell::RefCell;
+#[cfg(unix)]
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::ffi::CStr;
+use std::fs;
 use std::ops::Index;
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::time::SystemTime;
 
 pub const MAIN_WINDOW_NAME: &str = "main-window";
 
@@ -95,6 +106,58 @@ pub fn format_number_full(nb: u64, use_unit: bool) -> String {
     }
 }
 
+/// Returns the number of seconds since UNIX epoch.
+pub fn get_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("couldn't get start time")
+        .as_secs()
+}
+
+/// Formats a timestamp (in seconds since UNIX epoch) using the local timezone.
+pub fn format_date(timestamp: u64) -> String {
+    glib::DateTime::new_from_unix_local(timestamp as i64)
+        .format("%Y-%m-%d %H:%M:%S")
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Identifies a process: once a process is dead, its PID can be reused by another one so the PID
+/// alone isn't enough.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProcessIdentity {
+    pub pid: Pid,
+    pub start_time: u64,
+    pub exe: String,
+}
+
+impl ProcessIdentity {
+    pub fn new(process: &sysinfo::Process) -> ProcessIdentity {
+        ProcessIdentity {
+            pid: process.pid(),
+            start_time: process.start_time(),
+            exe: process.exe().to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Returns `false` if `process` is another process which got the same PID.
+    pub fn is_same_process(&self, process: &sysinfo::Process) -> bool {
+        *self == ProcessIdentity::new(process)
+    }
+}
+
+/// Returns the number of threads of the process if available.
+#[cfg(target_os = "linux")]
+pub fn get_thread_count(process: &sysinfo::Process) -> Option<u32> {
+    // The main thread isn't part of the tasks.
+    Some(process.tasks.len() as u32 + 1)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_thread_count(_process: &sysinfo::Process) -> Option<u32> {
+    None
+}
+
 pub fn connect_graph(graph: Graph) -> Rc<RefCell<Graph>> {
     let area = graph.area.clone();
     let graph = Rc::new(RefCell::new(graph));
@@ -134,6 +197,55 @@ pub fn get_main_window() -> Option<gtk::Window> {
     None
 }
 
+#[cfg(unix)]
+thread_local! {
+    static USER_NAMES: RefCell<HashMap<libc::uid_t, String>> = RefCell::new(HashMap::new());
+}
+
+#[cfg(unix)]
+fn get_user_name_from_uid(uid: libc::uid_t) -> Option<String> {
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+
+    let ret = unsafe {
+        libc::getpwuid_r(
+            uid,
+            &mut passwd,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    };
+    if ret != 0 || result.is_null() || passwd.pw_name.is_null() {
+        return None;
+    }
+    Some(
+        unsafe { CStr::from_ptr(passwd.pw_name) }
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+/// Returns the name of the user owning the process (or its uid if the user cannot be found).
+#[cfg(unix)]
+pub fn get_process_user(process: &sysinfo::Process) -> String {
+    USER_NAMES.with(|names| {
+        names
+            .borrow_mut()
+            .entry(process.uid)
+            .or_insert_with(|| {
+                get_user_name_from_uid(process.uid).unwrap_or_else(|| process.uid.to_string())
+            })
+            .clone()
+    })
+}
+
+#[cfg(windows)]
+pub fn get_process_user(_process: &sysinfo::Process) -> String {
+    String::new()
+}
+
 pub fn create_button_with_image(image_bytes: &'static [u8], fallback_text: &str) -> gtk::Button {
     let button = gtk::Button::new();
     let memory_stream = MemoryInputStream::new_from_bytes(&Bytes::from_static(image_bytes));
@@ -148,3 +260,76 @@ pub fn create_button_with_image(image_bytes: &'static [u8], fallback_text: &str)
     }
     button
 }
+
+/// Asks the user where to save `content`. Returns `None` if the user cancelled, otherwise the
+/// path of the written file or the error message.
+pub fn save_to_file(
+    parent: Option<&gtk::Window>,
+    title: &str,
+    file_name: &str,
+    content: &str,
+) -> Option<Result<PathBuf, String>> {
+    let dialog = gtk::FileChooserDialog::with_buttons(
+        Some(title),
+        parent,
+        gtk::FileChooserAction::Save,
+        &[
+            ("Cancel", gtk::ResponseType::Cancel),
+            ("Save", gtk::ResponseType::Accept),
+        ],
+    );
+    dialog.set_do_overwrite_confirmation(true);
+    dialog.set_current_name(file_name);
+    let path = if dialog.run() == gtk::ResponseType::Accept {
+        dialog.get_filename()
+    } else {
+        None
+    };
+    dialog.destroy();
+
+    let path = path?;
+    Some(match fs::write(&path, content) {
+        Ok(()) => Ok(path),
+        Err(e) => Err(format!("Cannot write {}: {}", path.display(), e)),
+    })
+}
+
+/// A bar displayed at the bottom of a tab to report the result of an action to the user.
+#[derive(Clone)]
+pub struct StatusBar {
+    pub info_bar: gtk::InfoBar,
+    label: gtk::Label,
+}
+
+impl StatusBar {
+    pub fn new() -> StatusBar {
+        let info_bar = gtk::InfoBar::new();
+        let label = gtk::Label::new(None);
+
+        label.set_line_wrap(true);
+        label.set_selectable(true);
+        label.show();
+        if let Some(content) = info_bar.get_content_area() {
+            match content.downcast::<gtk::Container>() {
+                Ok(content) => content.add(&label),
+                Err(e) => eprintln!(
+                    "<StatusBar::new> Failed to get info bar content area: {}",
+                    e
+                ),
+            }
+        }
+        info_bar.set_show_close_button(true);
+        info_bar.connect_response(|info_bar, _| {
+            info_bar.hide();
+        });
+        // We don't want it to be displayed when its parent is.
+        info_bar.set_no_show_all(true);
+        StatusBar { info_bar, label }
+    }
+
+    pub fn show_message(&self, message_type: gtk::MessageType, text: &str) {
+        self.info_bar.set_message_type(message_type);
+        self.label.set_text(text);
+        self.info_bar.show();
+    }
+}